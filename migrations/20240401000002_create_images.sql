@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS images (
+    id UUID PRIMARY KEY NOT NULL DEFAULT (uuid_generate_v4()),
+    name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS images_name_idx ON images (name);
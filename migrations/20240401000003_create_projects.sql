@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS projects (
+    id UUID PRIMARY KEY NOT NULL DEFAULT (uuid_generate_v4()),
+    title VARCHAR(255) NOT NULL,
+    description TEXT NOT NULL,
+    imgs TEXT[] NOT NULL DEFAULT '{}',
+    demo TEXT NOT NULL,
+    git TEXT NOT NULL,
+    stacks TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS jobs (
+    id UUID PRIMARY KEY NOT NULL DEFAULT (uuid_generate_v4()),
+    company VARCHAR(255) NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    date VARCHAR(255) NOT NULL,
+    description TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS details (
+    id UUID PRIMARY KEY NOT NULL DEFAULT (uuid_generate_v4()),
+    title VARCHAR(255) NOT NULL,
+    logo TEXT NOT NULL,
+    keywords TEXT NOT NULL,
+    site_description TEXT NOT NULL,
+    description TEXT NOT NULL,
+    about TEXT NOT NULL,
+    position VARCHAR(255) NOT NULL,
+    company VARCHAR(255) NOT NULL,
+    img TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS testimonials (
+    id UUID PRIMARY KEY NOT NULL DEFAULT (uuid_generate_v4()),
+    name VARCHAR(255) NOT NULL,
+    comment TEXT NOT NULL,
+    position VARCHAR(255) NOT NULL,
+    company VARCHAR(255) NOT NULL,
+    img TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
@@ -0,0 +1,16 @@
+-- Same definition PostgresStore::migrate uses, created here so user_sessions can reference it
+CREATE SCHEMA IF NOT EXISTS tower_sessions;
+
+CREATE TABLE IF NOT EXISTS tower_sessions.session (
+    id TEXT PRIMARY KEY NOT NULL,
+    data BYTEA NOT NULL,
+    expiry_date TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS user_sessions (
+    session_id TEXT PRIMARY KEY NOT NULL REFERENCES tower_sessions.session (id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS user_sessions_user_id_idx ON user_sessions (user_id);
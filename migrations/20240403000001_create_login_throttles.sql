@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS login_throttles (
+    id UUID PRIMARY KEY NOT NULL DEFAULT (uuid_generate_v4()),
+    scope VARCHAR(16) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    failures INTEGER NOT NULL DEFAULT 0,
+    last_failure_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMP WITH TIME ZONE,
+    UNIQUE (scope, subject)
+);
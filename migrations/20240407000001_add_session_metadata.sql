@@ -0,0 +1,7 @@
+-- Public handle for a session, session_id is the cookie value and must never be exposed
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS id UUID NOT NULL DEFAULT (uuid_generate_v4());
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS ip VARCHAR(64);
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS user_agent TEXT;
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS last_seen_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW();
+
+CREATE UNIQUE INDEX IF NOT EXISTS user_sessions_id_idx ON user_sessions (id);
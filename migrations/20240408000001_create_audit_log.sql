@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS audit_log (
+    id UUID PRIMARY KEY NOT NULL DEFAULT (uuid_generate_v4()),
+    -- Kept when the acting user is deleted, only the link is lost
+    actor_id UUID REFERENCES users (id) ON DELETE SET NULL,
+    action VARCHAR(32) NOT NULL,
+    resource VARCHAR(64) NOT NULL,
+    resource_id UUID,
+    -- {"before": {...}, "after": {...}} with secrets redacted
+    changes JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_created_at_idx ON audit_log (created_at);
+CREATE INDEX IF NOT EXISTS audit_log_resource_idx ON audit_log (resource, resource_id);
+CREATE INDEX IF NOT EXISTS audit_log_actor_id_idx ON audit_log (actor_id);
@@ -0,0 +1,2 @@
+-- Widths of the scaled down copies stored next to the image, see images.variant_widths
+ALTER TABLE images ADD COLUMN IF NOT EXISTS variants INTEGER[] NOT NULL DEFAULT '{}';
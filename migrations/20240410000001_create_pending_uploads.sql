@@ -0,0 +1,18 @@
+-- Where the file lives in storage. Uploads get a generated key, images registered before keep their name
+ALTER TABLE images ADD COLUMN IF NOT EXISTS storage_key TEXT;
+UPDATE images SET storage_key = name WHERE storage_key IS NULL;
+ALTER TABLE images ALTER COLUMN storage_key SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS images_storage_key_idx ON images (storage_key);
+
+-- Uploads not yet registered as an image, removed with their files once expires_at passes
+CREATE TABLE IF NOT EXISTS pending_uploads (
+    id UUID PRIMARY KEY NOT NULL DEFAULT (uuid_generate_v4()),
+    storage_key TEXT NOT NULL,
+    variants INTEGER[] NOT NULL DEFAULT '{}',
+    uploaded_by UUID REFERENCES users (id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS pending_uploads_expires_at_idx ON pending_uploads (expires_at);
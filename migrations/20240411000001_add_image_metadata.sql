@@ -0,0 +1,19 @@
+-- Filled in when a file is registered. Images registered before stay NULL until update_all_images reads them
+ALTER TABLE images
+    ADD COLUMN IF NOT EXISTS width INTEGER,
+    ADD COLUMN IF NOT EXISTS height INTEGER,
+    ADD COLUMN IF NOT EXISTS mime_type VARCHAR(100),
+    ADD COLUMN IF NOT EXISTS byte_size BIGINT,
+    ADD COLUMN IF NOT EXISTS sha256 VARCHAR(64),
+    -- Blurhash (https://blurha.sh) the frontend shows until the image has loaded
+    ADD COLUMN IF NOT EXISTS placeholder VARCHAR(100),
+    ADD COLUMN IF NOT EXISTS alt_text VARCHAR(500) NOT NULL DEFAULT '',
+    ADD COLUMN IF NOT EXISTS caption VARCHAR(2000) NOT NULL DEFAULT '';
+
+ALTER TABLE pending_uploads
+    ADD COLUMN IF NOT EXISTS width INTEGER,
+    ADD COLUMN IF NOT EXISTS height INTEGER,
+    ADD COLUMN IF NOT EXISTS mime_type VARCHAR(100),
+    ADD COLUMN IF NOT EXISTS byte_size BIGINT,
+    ADD COLUMN IF NOT EXISTS sha256 VARCHAR(64),
+    ADD COLUMN IF NOT EXISTS placeholder VARCHAR(100);
@@ -0,0 +1,18 @@
+-- One row per stored file, counting the images and pending uploads that point at it. The file goes
+-- with the last of them
+CREATE TABLE IF NOT EXISTS image_blobs (
+    storage_key TEXT PRIMARY KEY NOT NULL,
+    variants INTEGER[] NOT NULL DEFAULT '{}',
+    ref_count INTEGER NOT NULL DEFAULT 0 CHECK (ref_count >= 0),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO image_blobs (storage_key, variants, ref_count)
+SELECT DISTINCT ON (storage_key) storage_key, variants, COUNT(*) OVER (PARTITION BY storage_key)
+FROM (
+    SELECT storage_key, variants FROM images
+    UNION ALL
+    SELECT storage_key, variants FROM pending_uploads
+) refs
+ORDER BY storage_key, cardinality(variants) DESC
+ON CONFLICT (storage_key) DO NOTHING;
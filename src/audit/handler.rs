@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    response::IntoResponse,
+};
+
+use crate::extract::{Json, Query};
+use crate::audit::{model::AuditModel, schema::AuditFilterOptions};
+use crate::error::ApiError;
+use crate::general::schema::Table;
+use crate::AppState;
+
+// Newest first, every filter is optional
+pub async fn audit_list_handler(
+    Query(opts): Query<AuditFilterOptions>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (limit, offset) = data.config.pagination.page(opts.page, opts.limit);
+
+    let count = sqlx::query_as!(
+        Table,
+        "SELECT count(id) as count FROM audit_log
+        WHERE ($1::uuid IS NULL OR actor_id = $1) AND ($2::text IS NULL OR action = $2)
+            AND ($3::text IS NULL OR resource = $3) AND ($4::uuid IS NULL OR resource_id = $4)",
+        opts.actor_id,
+        opts.action,
+        opts.resource,
+        opts.resource_id
+    )
+    .fetch_one(&data.db)
+    .await?
+    .count;
+
+    let items = sqlx::query_as!(
+        AuditModel,
+        "SELECT id,actor_id,action,resource,resource_id,changes,created_at FROM audit_log
+        WHERE ($1::uuid IS NULL OR actor_id = $1) AND ($2::text IS NULL OR action = $2)
+            AND ($3::text IS NULL OR resource = $3) AND ($4::uuid IS NULL OR resource_id = $4)
+        ORDER BY created_at DESC, id LIMIT $5 OFFSET $6",
+        opts.actor_id,
+        opts.action,
+        opts.resource,
+        opts.resource_id,
+        limit,
+        offset
+    )
+    .fetch_all(&data.db)
+    .await?;
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "count": count,
+        "items": items
+    });
+    Ok(Json(json_response))
+}
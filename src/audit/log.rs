@@ -0,0 +1,119 @@
+use serde_json::{Map, Value};
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+// Values of these fields never reach the log, only the fact that they changed
+const REDACTED_FIELDS: &[&str] = &["password", "totp_secret", "token_hash", "code_hash"];
+const REDACTED: &str = "[redacted]";
+// Bumped on every write, recording them would only add noise
+const IGNORED_FIELDS: &[&str] = &["created_at", "updated_at", "createdAt", "updatedAt"];
+
+#[derive(Debug, Clone, Copy)]
+pub enum Action {
+    Create,
+    Update,
+    Delete,
+    Upload,
+    #[cfg(feature = "dev-impersonation")]
+    Impersonate,
+}
+
+impl Action {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Create => "create",
+            Action::Update => "update",
+            Action::Delete => "delete",
+            Action::Upload => "upload",
+            #[cfg(feature = "dev-impersonation")]
+            Action::Impersonate => "impersonate",
+        }
+    }
+}
+
+pub struct Entry<'a> {
+    pub actor_id: Option<Uuid>,
+    pub action: Action,
+    pub resource: &'a str,
+    pub resource_id: Option<Uuid>,
+    pub changes: Value,
+}
+
+// Failures are logged rather than returned, the change itself already went through
+pub async fn record(db: &Pool<Postgres>, entry: Entry<'_>) {
+    let result = sqlx::query!(
+        "INSERT INTO audit_log (actor_id,action,resource,resource_id,changes) VALUES ($1, $2, $3, $4, $5)",
+        entry.actor_id,
+        entry.action.as_str(),
+        entry.resource,
+        entry.resource_id,
+        entry.changes
+    )
+    .execute(db)
+    .await;
+
+    if let Err(e) = result {
+        eprintln!(
+            "Audit log write failed for {} {} {:?}: {}",
+            entry.action.as_str(),
+            entry.resource,
+            entry.resource_id,
+            e
+        );
+    }
+}
+
+// {"before": {...}, "after": {...}} holding only the fields that differ. `written` lists columns
+// the request set, so changes to fields the model never serializes (passwords) still show up
+pub fn diff(before: Option<&Value>, after: Option<&Value>, written: &[&str]) -> Value {
+    let empty = Map::new();
+    let before = before.and_then(Value::as_object).unwrap_or(&empty);
+    let after = after.and_then(Value::as_object).unwrap_or(&empty);
+
+    let mut old = Map::new();
+    let mut new = Map::new();
+
+    for key in before.keys().chain(after.keys()) {
+        if IGNORED_FIELDS.contains(&key.as_str()) || old.contains_key(key) || new.contains_key(key) {
+            continue;
+        }
+        let (old_value, new_value) = (before.get(key), after.get(key));
+        if old_value == new_value {
+            continue;
+        }
+        if let Some(value) = old_value {
+            old.insert(key.clone(), redact(key, value));
+        }
+        if let Some(value) = new_value {
+            new.insert(key.clone(), redact(key, value));
+        }
+    }
+
+    for column in written {
+        if REDACTED_FIELDS.contains(column) && !after.contains_key(*column) {
+            new.insert(column.to_string(), Value::from(REDACTED));
+        }
+    }
+
+    let mut changes = Map::new();
+    if !old.is_empty() {
+        changes.insert("before".to_string(), Value::Object(old));
+    }
+    if !new.is_empty() {
+        changes.insert("after".to_string(), Value::Object(new));
+    }
+    Value::Object(changes)
+}
+
+fn redact(key: &str, value: &Value) -> Value {
+    if REDACTED_FIELDS.contains(&key) {
+        Value::from(REDACTED)
+    } else {
+        value.clone()
+    }
+}
+
+// The "id" of a serialized model
+pub fn id_of(value: &Value) -> Option<Uuid> {
+    value.get("id")?.as_str()?.parse().ok()
+}
@@ -0,0 +1,5 @@
+pub mod handler;
+pub mod log;
+pub mod model;
+pub mod schema;
+pub mod route;
\ No newline at end of file
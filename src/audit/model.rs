@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+pub struct AuditModel {
+    pub id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub action: String,
+    pub resource: String,
+    pub resource_id: Option<Uuid>,
+    pub changes: serde_json::Value,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,16 @@
+use std::sync::Arc;
+
+use axum::{middleware, routing::get, Router};
+
+use crate::{
+    audit::handler::audit_list_handler,
+    auth::{role::Access, route::require_permission},
+    AppState,
+};
+
+pub fn audit_router(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/audit", get(audit_list_handler))
+        .route_layer(middleware::from_fn_with_state(Access::USERS, require_permission))
+        .with_state(app_state)
+}
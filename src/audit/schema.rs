@@ -0,0 +1,12 @@
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize, Debug, Default)]
+pub struct AuditFilterOptions {
+    pub page: Option<usize>,
+    pub limit: Option<usize>,
+    pub actor_id: Option<Uuid>,
+    pub action: Option<String>,
+    pub resource: Option<String>,
+    pub resource_id: Option<Uuid>,
+}
@@ -0,0 +1,81 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+
+use crate::AppState;
+
+// Address of the caller, from X-Forwarded-For when `trust_proxy` is set. The client can write
+// anything into that header and proxies only append to it, so the address is the entry the
+// outermost trusted proxy added, `trusted_proxy_hops` from the right
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for ClientIp {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        if state.config.trust_proxy {
+            // Repeated headers count as one list, in order
+            let entries: Vec<&str> = parts
+                .headers
+                .get_all("x-forwarded-for")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .collect();
+            let forwarded = entries
+                .iter()
+                .rev()
+                .nth(state.config.trusted_proxy_hops - 1)
+                .and_then(|value| value.trim().parse::<IpAddr>().ok());
+
+            if let Some(ip) = forwarded {
+                return Ok(ClientIp(ip));
+            }
+        }
+
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+        Ok(ClientIp(ip))
+    }
+}
+
+// Where a request comes from, recorded with each session
+pub struct ClientInfo {
+    pub ip: IpAddr,
+    pub user_agent: String,
+}
+
+// Longer user agents are cut, they're only for display
+const MAX_USER_AGENT_CHARS: usize = 512;
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for ClientInfo {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        let ClientIp(ip) = ClientIp::from_request_parts(parts, state).await?;
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .chars()
+            .take(MAX_USER_AGENT_CHARS)
+            .collect();
+
+        Ok(ClientInfo { ip, user_agent })
+    }
+}
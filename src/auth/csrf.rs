@@ -0,0 +1,48 @@
+use axum::http::{HeaderMap, HeaderName, Method};
+use tower_sessions::Session;
+
+use crate::{
+    auth::{password::constant_time_eq, token::random_token},
+    error::ApiError,
+};
+
+pub const CSRF_HEADER: HeaderName = HeaderName::from_static("x-csrf-token");
+const CSRF_SESSION_KEY: &str = "csrf_token";
+
+// Replaces the session's token, called whenever a session is (re)started
+pub async fn issue_csrf_token(session: &Session) -> Result<String, ApiError> {
+    let token = random_token();
+
+    session
+        .insert(CSRF_SESSION_KEY, &token)
+        .await
+        .map_err(ApiError::internal)?;
+
+    Ok(token)
+}
+
+pub async fn csrf_token(session: &Session) -> Result<String, ApiError> {
+    match session.get::<String>(CSRF_SESSION_KEY).await {
+        Ok(Some(token)) => Ok(token),
+        _ => issue_csrf_token(session).await,
+    }
+}
+
+// Mutating requests must echo the session's token in the X-CSRF-Token header
+pub async fn verify_csrf(session: &Session, method: &Method, headers: &HeaderMap) -> Result<(), ApiError> {
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        return Ok(());
+    }
+
+    let sent = headers
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| ApiError::Forbidden("Missing CSRF token, send it in the X-CSRF-Token header".to_string()))?;
+
+    let expected = session.get::<String>(CSRF_SESSION_KEY).await.ok().flatten();
+
+    match expected {
+        Some(expected) if constant_time_eq(sent.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => Err(ApiError::Forbidden("Invalid CSRF token, fetch a new one from /auth/csrf".to_string())),
+    }
+}
@@ -4,90 +4,315 @@ use axum::{
     extract::State,
     http::StatusCode,
     response::IntoResponse,
-    Json,
+    Extension,
 };
 
-use crate::auth::schema::Login;
+use crate::extract::{Json, Path};
+use crate::auth::client::ClientInfo;
+use crate::auth::password::{hash_password, verify_password, Verification};
+use crate::auth::model::{ProfileModel, SessionModel};
+use crate::auth::password::validate_password;
+use crate::auth::role::{Principal, Role};
+use crate::auth::schema::{ChangePasswordSchema, Login, UpdateProfileSchema};
+use crate::auth::csrf::csrf_token;
+use crate::auth::session::{
+    revoke_other_sessions, revoke_user_sessions, rotate_session, start_session, start_two_factor, validate_session,
+};
+use crate::auth::throttle::{self, Scope};
+use crate::error::ApiError;
 use crate::AppState;
 
-use bcrypt::verify;
 use tower_sessions::Session;
 
 use super::schema::User;
 
-pub async fn test_login_handler(
+pub async fn login_handler(
     session: Session,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    session.insert("logged_in",1).await.unwrap();
+    State(data): State<Arc<AppState>>,
+    client: ClientInfo,
+    Json(body): Json<Login>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (Some(email), Some(password)) = (body.email, body.password) else {
+        return Err(ApiError::Validation("email and password are required".to_string()));
+    };
+
+    let ip = client.ip.to_string();
+    let account = email.trim().to_lowercase();
+
+    throttle::check(&data.db, Scope::Ip, &ip).await?;
+    throttle::check(&data.db, Scope::Account, &account).await?;
+
+    let item = sqlx::query_as!(
+        User,
+        "SELECT id,username,email,password,fullname,role,avatar,active,totp_enabled FROM users WHERE LOWER(email)=$1",
+        account
+    )
+    .fetch_optional(&data.db)
+    .await?;
+
+    let verification = match &item {
+        Some(item) => verify_password(&password, &item.password, data.config.password_cost).await,
+        None => {
+            // Spend the same time as a real check so unknown emails can't be told apart
+            let _ = hash_password(&password, data.config.password_cost).await;
+            Verification::Invalid
+        }
+    };
+
+    let item = match (item, verification) {
+        (Some(item), Verification::Valid) => item,
+        (Some(item), Verification::NeedsRehash) => {
+            // Upgrade legacy plaintext or low-cost hashes now that we know the password
+            if let Ok(password_hash) = hash_password(&password, data.config.password_cost).await {
+                let _ = sqlx::query!(
+                    "UPDATE users SET password = $1 WHERE id = $2",
+                    password_hash,
+                    item.id
+                )
+                .execute(&data.db)
+                .await;
+            }
+            item
+        }
+        _ => {
+            throttle::record_failure(&data.db, &data.config.login, Scope::Ip, &ip).await?;
+            throttle::record_failure(&data.db, &data.config.login, Scope::Account, &account).await?;
+            return Err(ApiError::Unauthorized("Invalid email or password".to_string()));
+        }
+    };
+
+    if item.active == 0 {
+        return Err(ApiError::Forbidden("Account is disabled".to_string()));
+    }
+
+    // The account counter is only cleared once the second factor is in too, otherwise someone
+    // with the password could keep guessing codes
+    if item.totp_enabled {
+        start_two_factor(&session, item.id).await?;
+
+        let json_response = serde_json::json!({
+            "status": "success",
+            "two_factor_required": true
+        });
+
+        return Ok((StatusCode::OK, Json(json_response)));
+    }
+
+    throttle::clear(&data.db, Scope::Account, &account).await?;
+
+    let csrf_token = start_session(&session, &data.db, &item, &client).await?;
+
+    let setup_required = data.config.two_factor.require_for_admins
+        && Role::try_from(item.role) == Ok(Role::Admin);
 
     let json_response = serde_json::json!({
         "status": "success",
+        "data": ProfileModel::from(&item),
+        "csrf_token": csrf_token,
+        "two_factor_setup_required": setup_required
     });
+
     Ok((StatusCode::OK, Json(json_response)))
 }
 
-pub async fn login_handler(
+// Returns the session's CSRF token, for clients that lost the one sent at login
+pub async fn csrf_handler(
     session: Session,
     State(data): State<Arc<AppState>>,
-    Json(body): Json<Login>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    let email = body.email.unwrap();
-    let password = body.password.unwrap();
+) -> Result<impl IntoResponse, ApiError> {
+    validate_session(&session, &data.db).await?;
 
-    let query_result = sqlx::query_as!(
-        User,
-        "SELECT id,username,email,password,fullname,role,avatar,active FROM users WHERE email=$1",
-        email
+    let csrf_token = csrf_token(&session).await?;
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "data": {
+            "csrf_token": csrf_token
+        }
+    });
+
+    Ok((StatusCode::OK, Json(json_response)))
+}
+
+pub async fn me_handler(
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let item = sqlx::query_as!(
+        ProfileModel,
+        "SELECT id,username,email,fullname,role,avatar,totp_enabled FROM users WHERE id = $1",
+        principal.id
     )
-    .fetch_one(&data.db)
-    .await;
+    .fetch_optional(&data.db)
+    .await?
+    .ok_or_else(|| ApiError::not_found(principal.id))?;
 
-    if query_result.is_err() {
-        let error_response = serde_json::json!({
-            "status": "fail",
-            "message": "User not exists",
-        });
-        return Err((StatusCode::NOT_FOUND, Json(error_response)));
+    let item_response = serde_json::json!({"status": "success","data": serde_json::json!({
+        "item": item
+    })});
+
+    Ok(Json(item_response))
+}
+
+pub async fn update_me_handler(
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<UpdateProfileSchema>,
+) -> Result<impl IntoResponse, ApiError> {
+    if principal.scopes.is_some() {
+        return Err(ApiError::Forbidden("Log in to edit your profile".to_string()));
+    }
+    if let Some(username) = &body.username {
+        if username.trim().is_empty() {
+            return Err(ApiError::Validation("username must not be empty".to_string()));
+        }
     }
 
-    let item = query_result.unwrap();
+    let item = sqlx::query_as!(
+        ProfileModel,
+        "UPDATE users SET username = COALESCE($1, username), fullname = COALESCE($2, fullname),
+            avatar = COALESCE($3, avatar), updated_at = NOW()
+        WHERE id = $4 RETURNING id,username,email,fullname,role,avatar,totp_enabled",
+        body.username.map(|username| username.trim().to_string()),
+        body.fullname,
+        body.avatar,
+        principal.id
+    )
+    .fetch_optional(&data.db)
+    .await?
+    .ok_or_else(|| ApiError::not_found(principal.id))?;
 
-    let password_hash = item.password.clone();
-    let valid = verify(&password, &password_hash).unwrap();
+    let item_response = serde_json::json!({"status": "success","data": serde_json::json!({
+        "item": item
+    })});
 
-    if !valid {
-        let error_response = serde_json::json!({
-            "status": "fail",
-            "message": "Passwords did not match",
-        });
-        return Err((StatusCode::NOT_ACCEPTABLE, Json(error_response)));
+    Ok(Json(item_response))
+}
+
+pub async fn change_password_handler(
+    session: Session,
+    client: ClientInfo,
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<ChangePasswordSchema>,
+) -> Result<impl IntoResponse, ApiError> {
+    // The session is rotated below, so this needs a cookie session rather than a token
+    if principal.scopes.is_some() {
+        return Err(ApiError::Forbidden("Log in to change your password".to_string()));
+    }
+
+    let item = sqlx::query_as!(
+        User,
+        "SELECT id,username,email,password,fullname,role,avatar,active,totp_enabled FROM users WHERE id=$1",
+        principal.id
+    )
+    .fetch_optional(&data.db)
+    .await?
+    .ok_or_else(|| ApiError::not_found(principal.id))?;
+
+    // Guessing the current password here counts against the same limits as the login form
+    let account = item.email.trim().to_lowercase();
+    throttle::check(&data.db, Scope::Account, &account).await?;
+
+    if let Verification::Invalid =
+        verify_password(&body.current_password, &item.password, data.config.password_cost).await
+    {
+        throttle::record_failure(&data.db, &data.config.login, Scope::Account, &account).await?;
+        return Err(ApiError::Validation("Current password is incorrect".to_string()));
     }
+    throttle::clear(&data.db, Scope::Account, &account).await?;
+
+    validate_password(&body.new_password).map_err(ApiError::Validation)?;
+    let password_hash =
+        hash_password(&body.new_password, data.config.password_cost).await.map_err(ApiError::internal)?;
+
+    sqlx::query!(
+        "UPDATE users SET password = $1, updated_at = NOW() WHERE id = $2",
+        password_hash,
+        item.id
+    )
+    .execute(&data.db)
+    .await?;
+
+    let csrf_token = rotate_session(&session, &data.db, item.id, &client).await?;
+    revoke_other_sessions(&data.db, item.id, &session).await?;
 
     let json_response = serde_json::json!({
         "status": "success",
-        "data": item
+        "csrf_token": csrf_token
     });
 
-    session.insert("logged_in",1).await.unwrap();
-    session.insert("id",item.id).await.unwrap();
-    session.insert("email",item.email).await.unwrap();
-    session.insert("username",item.username).await.unwrap();
-    session.insert("fullname",item.fullname).await.unwrap();
-    session.insert("role",item.role).await.unwrap();
-    session.insert("avatar",item.avatar).await.unwrap();
-    session.insert("active",item.active).await.unwrap();
-
     Ok((StatusCode::OK, Json(json_response)))
 }
 
+pub async fn session_list_handler(
+    session: Session,
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let current = session.id().map(|id| id.to_string()).unwrap_or_default();
+
+    let items = sqlx::query_as!(
+        SessionModel,
+        r#"SELECT us.id, us.ip, us.user_agent, us.created_at, us.last_seen_at, us.session_id = $2 AS "current!"
+        FROM user_sessions us JOIN tower_sessions.session s ON s.id = us.session_id
+        WHERE us.user_id = $1 AND s.expiry_date > NOW() ORDER BY us.last_seen_at DESC"#,
+        principal.id,
+        current
+    )
+    .fetch_all(&data.db)
+    .await?;
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "count": items.len(),
+        "items": items
+    });
+    Ok(Json(json_response))
+}
+
+pub async fn delete_session_handler(
+    Path(id): Path<uuid::Uuid>,
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    // user_sessions rows go with the session through ON DELETE CASCADE
+    let rows_affected = sqlx::query!(
+        "DELETE FROM tower_sessions.session WHERE id = (SELECT session_id FROM user_sessions WHERE id = $1 AND user_id = $2)",
+        id,
+        principal.id
+    )
+    .execute(&data.db)
+    .await?
+    .rows_affected();
+
+    if rows_affected == 0 {
+        return Err(ApiError::not_found(id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Log out everywhere, this session included
+pub async fn delete_all_sessions_handler(
+    session: Session,
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    revoke_user_sessions(&data.db, principal.id).await?;
+    session.flush().await.map_err(ApiError::internal)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub async fn logout_handler(
     session: Session,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    session.flush().await.unwrap();
+) -> Result<impl IntoResponse, ApiError> {
+    session.flush().await.map_err(ApiError::internal)?;
 
     let json_response = serde_json::json!({
         "status": "success"
     });
 
     Ok((StatusCode::OK, Json(json_response)))
-}
\ No newline at end of file
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+};
+use tower_sessions::Session;
+
+use crate::extract::{Json, Path};
+use crate::audit::log::{self, Action, Entry};
+use crate::auth::client::ClientInfo;
+use crate::auth::model::ProfileModel;
+use crate::auth::schema::User;
+use crate::auth::session::start_session;
+use crate::error::ApiError;
+use crate::AppState;
+
+pub const BANNER: &str = "\
+##################################################################
+#  WARNING: dev-impersonation is enabled.                        #
+#  POST /auth/impersonate/:id logs in as any user without a      #
+#  password. Never run this build in production.                 #
+##################################################################";
+
+// Logs in as an existing user without credentials, for local development only
+pub async fn impersonate_handler(
+    Path(id): Path<uuid::Uuid>,
+    session: Session,
+    State(data): State<Arc<AppState>>,
+    client: ClientInfo,
+) -> Result<impl IntoResponse, ApiError> {
+    let item = sqlx::query_as!(
+        User,
+        "SELECT id,username,email,password,fullname,role,avatar,active,totp_enabled FROM users WHERE id=$1",
+        id
+    )
+    .fetch_optional(&data.db)
+    .await?
+    .ok_or_else(|| ApiError::not_found(id))?;
+
+    if item.active == 0 {
+        return Err(ApiError::Forbidden("Account is disabled".to_string()));
+    }
+
+    eprintln!(
+        "AUDIT impersonation: {} started a session as user {} ({})",
+        client.ip, item.id, item.email
+    );
+    log::record(
+        &data.db,
+        Entry {
+            actor_id: None,
+            action: Action::Impersonate,
+            resource: "users",
+            resource_id: Some(item.id),
+            changes: serde_json::json!({"ip": client.ip.to_string()}),
+        },
+    )
+    .await;
+
+    let csrf_token = start_session(&session, &data.db, &item, &client).await?;
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "data": ProfileModel::from(&item),
+        "csrf_token": csrf_token
+    });
+
+    Ok((StatusCode::OK, Json(json_response)))
+}
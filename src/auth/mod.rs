@@ -1,4 +1,17 @@
+pub mod client;
+pub mod csrf;
 pub mod handler;
+#[cfg(feature = "dev-impersonation")]
+pub mod impersonate;
 pub mod model;
+pub mod oidc;
+pub mod password;
+pub mod reset;
+pub mod role;
 pub mod schema;
+pub mod session;
+pub mod throttle;
+pub mod token;
+pub mod totp;
+pub mod two_factor;
 pub mod route;
\ No newline at end of file
@@ -2,20 +2,42 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use crate::auth::schema::User;
+
+// What a user gets to see about their own account
 #[derive(Debug, FromRow, Deserialize, Serialize)]
-#[allow(non_snake_case)]
-pub struct UserModel {
+pub struct ProfileModel {
     pub id: Uuid,
     pub username: String,
-    pub password: String,
     pub email: String,
     pub fullname: String,
     pub role: i16,
     pub avatar: String,
-    pub notes: String,
-    pub active: i16,
-    #[serde(rename = "createdAt")]
-    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
-    #[serde(rename = "updatedAt")]
-    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
-}
\ No newline at end of file
+    #[serde(rename = "two_factor_enabled")]
+    pub totp_enabled: bool,
+}
+
+impl From<&User> for ProfileModel {
+    fn from(user: &User) -> Self {
+        ProfileModel {
+            id: user.id,
+            username: user.username.clone(),
+            email: user.email.clone(),
+            fullname: user.fullname.clone(),
+            role: user.role,
+            avatar: user.avatar.clone(),
+            totp_enabled: user.totp_enabled,
+        }
+    }
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+pub struct SessionModel {
+    pub id: Uuid,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_seen_at: chrono::DateTime<chrono::Utc>,
+    // The session the request was made with
+    pub current: bool,
+}
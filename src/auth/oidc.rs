@@ -0,0 +1,527 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use tower_sessions::Session;
+
+use crate::extract::{Json, Query};
+use crate::audit::log::{self, Action, Entry};
+use crate::auth::client::ClientInfo;
+use crate::auth::model::ProfileModel;
+use crate::auth::password::hash_password;
+use crate::auth::role::Role;
+use crate::auth::schema::User;
+use crate::auth::session::{start_session, start_two_factor};
+use crate::auth::token::random_token;
+use crate::config::OidcConfig;
+use crate::error::ApiError;
+use crate::AppState;
+
+const PENDING_LOGIN: &str = "oidc_login";
+// How long the user may spend at the provider before the callback is refused
+const LOGIN_TIMEOUT_SECONDS: i64 = 600;
+
+// What the callback needs to check the provider's answer, kept in the (still anonymous) session
+#[derive(Serialize, Deserialize, Debug)]
+struct PendingLogin {
+    state: String,
+    nonce: String,
+    code_verifier: String,
+    started: i64,
+}
+
+#[derive(Deserialize, Debug)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize, Debug)]
+struct Claims {
+    iss: String,
+    aud: Audience,
+    exp: i64,
+    nonce: Option<String>,
+    email: Option<String>,
+    email_verified: Option<bool>,
+    name: Option<String>,
+    preferred_username: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct CallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+fn enabled_config(data: &AppState) -> Result<&OidcConfig, ApiError> {
+    if !data.config.oidc.enabled {
+        return Err(ApiError::NotFound("Single sign-on is not enabled".to_string()));
+    }
+    Ok(&data.config.oidc)
+}
+
+async fn discover(config: &OidcConfig) -> Result<Discovery, ApiError> {
+    let url = format!(
+        "{}/.well-known/openid-configuration",
+        config.issuer.trim_end_matches('/')
+    );
+
+    let discovery: Discovery = reqwest::get(&url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(ApiError::internal)?
+        .json()
+        .await
+        .map_err(ApiError::internal)?;
+
+    if discovery.issuer.trim_end_matches('/') != config.issuer.trim_end_matches('/') {
+        return Err(ApiError::internal(format!(
+            "OIDC discovery returned issuer {:?}, expected {:?}",
+            discovery.issuer, config.issuer
+        )));
+    }
+
+    Ok(discovery)
+}
+
+fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+// The ID token comes straight from the token endpoint over TLS, which OIDC Core 3.1.3.7 accepts in
+// place of checking its signature, so only the claims are validated here
+fn validate_id_token(config: &OidcConfig, id_token: &str, nonce: &str) -> Result<Claims, ApiError> {
+    let payload = id_token
+        .split('.')
+        .nth(1)
+        .and_then(|payload| URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok())
+        .ok_or_else(|| ApiError::internal("OIDC provider returned a malformed ID token"))?;
+    let claims: Claims = serde_json::from_slice(&payload).map_err(ApiError::internal)?;
+
+    let audience_ok = match &claims.aud {
+        Audience::One(aud) => aud == &config.client_id,
+        Audience::Many(auds) => auds.contains(&config.client_id),
+    };
+
+    if claims.iss.trim_end_matches('/') != config.issuer.trim_end_matches('/')
+        || !audience_ok
+        || claims.exp < chrono::Utc::now().timestamp()
+        || claims.nonce.as_deref() != Some(nonce)
+    {
+        return Err(ApiError::Unauthorized("ID token is not valid for this login".to_string()));
+    }
+
+    Ok(claims)
+}
+
+// Sends the browser to the provider, the code comes back to oidc_callback_handler
+pub async fn oidc_login_handler(
+    session: Session,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let config = enabled_config(&data)?;
+    let discovery = discover(config).await?;
+
+    let pending = PendingLogin {
+        state: random_token(),
+        nonce: random_token(),
+        code_verifier: random_token(),
+        started: chrono::Utc::now().timestamp(),
+    };
+
+    let mut url = reqwest::Url::parse(&discovery.authorization_endpoint).map_err(ApiError::internal)?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &config.client_id)
+        .append_pair("redirect_uri", &config.redirect_uri)
+        .append_pair("scope", &config.scopes)
+        .append_pair("state", &pending.state)
+        .append_pair("nonce", &pending.nonce)
+        .append_pair("code_challenge", &code_challenge(&pending.code_verifier))
+        .append_pair("code_challenge_method", "S256");
+
+    session
+        .insert(PENDING_LOGIN, pending)
+        .await
+        .map_err(ApiError::internal)?;
+
+    Ok(Redirect::to(url.as_str()))
+}
+
+pub async fn oidc_callback_handler(
+    session: Session,
+    client: ClientInfo,
+    State(data): State<Arc<AppState>>,
+    Query(query): Query<CallbackQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let config = enabled_config(&data)?;
+
+    // Single use, a second callback with the same state finds nothing
+    let pending = session
+        .remove::<PendingLogin>(PENDING_LOGIN)
+        .await
+        .map_err(ApiError::internal)?
+        .ok_or_else(|| ApiError::Unauthorized("No single sign-on login is in progress".to_string()))?;
+
+    if let Some(error) = query.error {
+        return Err(ApiError::Unauthorized(format!(
+            "Identity provider refused the login: {}",
+            query.error_description.unwrap_or(error)
+        )));
+    }
+    if query.state.as_deref() != Some(pending.state.as_str()) {
+        return Err(ApiError::Unauthorized("Login state does not match".to_string()));
+    }
+    if chrono::Utc::now().timestamp() - pending.started > LOGIN_TIMEOUT_SECONDS {
+        return Err(ApiError::Unauthorized("Single sign-on login timed out, try again".to_string()));
+    }
+    let code = query
+        .code
+        .ok_or_else(|| ApiError::Validation("code is required".to_string()))?;
+
+    let (email, claims) = exchange_code(config, &pending, &code).await?;
+    let item = account_for(&data.db, data.config.password_cost, config, &email, &claims).await?;
+
+    if item.active == 0 {
+        return Err(ApiError::Forbidden("Account is disabled".to_string()));
+    }
+
+    // The provider vouches for the login, an enabled local second factor is still asked for
+    if item.totp_enabled {
+        start_two_factor(&session, item.id).await?;
+
+        let json_response = serde_json::json!({
+            "status": "success",
+            "two_factor_required": true
+        });
+
+        return Ok((StatusCode::OK, Json(json_response)));
+    }
+
+    let csrf_token = start_session(&session, &data.db, &item, &client).await?;
+
+    let setup_required = data.config.two_factor.require_for_admins
+        && Role::try_from(item.role) == Ok(Role::Admin);
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "data": ProfileModel::from(&item),
+        "csrf_token": csrf_token,
+        "two_factor_setup_required": setup_required
+    });
+
+    Ok((StatusCode::OK, Json(json_response)))
+}
+
+// Trades the code for the provider's claims, the PKCE verifier proves the login started here
+async fn exchange_code(config: &OidcConfig, pending: &PendingLogin, code: &str) -> Result<(String, Claims), ApiError> {
+    let discovery = discover(config).await?;
+
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", config.redirect_uri.as_str()),
+        ("client_id", config.client_id.as_str()),
+        ("code_verifier", pending.code_verifier.as_str()),
+    ];
+    if !config.client_secret.is_empty() {
+        form.push(("client_secret", config.client_secret.as_str()));
+    }
+
+    let response = reqwest::Client::new()
+        .post(&discovery.token_endpoint)
+        .form(&form)
+        .send()
+        .await
+        .map_err(ApiError::internal)?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        eprintln!("OIDC token exchange failed with {}: {}", status, body);
+        return Err(ApiError::Unauthorized(
+            "Identity provider rejected the authorization code".to_string(),
+        ));
+    }
+
+    let tokens: TokenResponse = response.json().await.map_err(ApiError::internal)?;
+    let claims = validate_id_token(config, &tokens.id_token, &pending.nonce)?;
+
+    // A provider that doesn't assert verification could hand out any address, and with it the
+    // account that has it
+    let email = match (claims.email.as_deref(), claims.email_verified) {
+        (Some(email), Some(true)) => email.trim().to_lowercase(),
+        (Some(_), _) => {
+            return Err(ApiError::Forbidden("Your email is not verified by the identity provider".to_string()))
+        }
+        (None, _) => {
+            return Err(ApiError::Forbidden("Identity provider did not share an email address".to_string()))
+        }
+    };
+
+    Ok((email, claims))
+}
+
+// The account with the verified email, created first when auto_provision allows it
+async fn account_for(
+    db: &PgPool,
+    password_cost: u32,
+    config: &OidcConfig,
+    email: &str,
+    claims: &Claims,
+) -> Result<User, ApiError> {
+    let item = sqlx::query_as!(
+        User,
+        "SELECT id,username,email,password,fullname,role,avatar,active,totp_enabled FROM users WHERE LOWER(email)=$1",
+        email
+    )
+    .fetch_optional(db)
+    .await?;
+
+    match item {
+        Some(item) => Ok(item),
+        None if config.auto_provision => provision_user(db, password_cost, config, email, claims).await,
+        None => Err(ApiError::Forbidden("No account exists for this email".to_string())),
+    }
+}
+
+// The random password is never shown, the account can get a real one through /auth/forgot
+async fn provision_user(
+    db: &PgPool,
+    password_cost: u32,
+    config: &OidcConfig,
+    email: &str,
+    claims: &Claims,
+) -> Result<User, ApiError> {
+    let username = claims
+        .preferred_username
+        .clone()
+        .unwrap_or_else(|| email.split('@').next().unwrap_or(email).to_string());
+    let fullname = claims.name.clone().unwrap_or_else(|| username.clone());
+    let password = hash_password(&random_token(), password_cost).await.map_err(ApiError::internal)?;
+
+    let item = sqlx::query_as!(
+        User,
+        "INSERT INTO users (username,email,password,fullname,role) VALUES ($1, $2, $3, $4, $5) RETURNING id,username,email,password,fullname,role,avatar,active,totp_enabled",
+        username,
+        email,
+        password,
+        fullname,
+        config.default_role
+    )
+    .fetch_one(db)
+    .await?;
+
+    log::record(
+        db,
+        Entry {
+            actor_id: Some(item.id),
+            action: Action::Create,
+            resource: "users",
+            resource_id: Some(item.id),
+            changes: serde_json::json!({"after": {
+                "username": item.username,
+                "email": item.email,
+                "fullname": item.fullname,
+                "role": item.role,
+                "provisioned_by": config.issuer
+            }}),
+        },
+    )
+    .await;
+
+    Ok(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::{
+        extract::{Form, State},
+        routing::{get, post},
+        Router,
+    };
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    const CODE: &str = "the-code";
+    const NONCE: &str = "the-nonce";
+    const VERIFIER: &str = "the-verifier";
+
+    // What the stand-in provider hands out for CODE once the verifier matches the challenge
+    struct Provider {
+        issuer: String,
+        challenge: String,
+        claims: Value,
+    }
+
+    async fn token(State(provider): State<Arc<Provider>>, Form(form): Form<HashMap<String, String>>) -> axum::response::Response {
+        let verifier = form.get("code_verifier").map(String::as_str).unwrap_or_default();
+        if form.get("grant_type").map(String::as_str) != Some("authorization_code")
+            || form.get("code").map(String::as_str) != Some(CODE)
+            || form.get("client_id").map(String::as_str) != Some("portfolio")
+            || code_challenge(verifier) != provider.challenge
+        {
+            return (StatusCode::BAD_REQUEST, axum::Json(json!({"error": "invalid_grant"}))).into_response();
+        }
+
+        let id_token = format!(
+            "{}.{}.signature",
+            URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256"}"#),
+            URL_SAFE_NO_PAD.encode(provider.claims.to_string())
+        );
+        axum::Json(json!({"access_token": "access", "token_type": "Bearer", "id_token": id_token})).into_response()
+    }
+
+    async fn discovery(State(provider): State<Arc<Provider>>) -> axum::Json<Value> {
+        axum::Json(json!({
+            "issuer": provider.issuer,
+            "authorization_endpoint": format!("{}/authorize", provider.issuer),
+            "token_endpoint": format!("{}/token", provider.issuer)
+        }))
+    }
+
+    // Serves discovery and the token endpoint on a free local port, returns the matching config
+    async fn provider(claims: impl FnOnce(&str) -> Value) -> OidcConfig {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+
+        let provider = Arc::new(Provider {
+            issuer: issuer.clone(),
+            challenge: code_challenge(VERIFIER),
+            claims: claims(&issuer),
+        });
+        let app = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/token", post(token))
+            .with_state(provider);
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        OidcConfig {
+            enabled: true,
+            issuer,
+            client_id: "portfolio".to_string(),
+            ..OidcConfig::default()
+        }
+    }
+
+    fn claims(issuer: &str, email_verified: Option<bool>) -> Value {
+        json!({
+            "iss": issuer,
+            "aud": "portfolio",
+            "exp": chrono::Utc::now().timestamp() + 300,
+            "nonce": NONCE,
+            "email": " Someone@Example.com",
+            "email_verified": email_verified,
+            "name": "Some One",
+            "preferred_username": "someone"
+        })
+    }
+
+    fn pending(code_verifier: &str) -> PendingLogin {
+        PendingLogin {
+            state: "state".to_string(),
+            nonce: NONCE.to_string(),
+            code_verifier: code_verifier.to_string(),
+            started: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    #[tokio::test]
+    async fn verified_email_is_accepted() {
+        let config = provider(|issuer| claims(issuer, Some(true))).await;
+
+        let (email, claims) = exchange_code(&config, &pending(VERIFIER), CODE).await.unwrap();
+        assert_eq!(email, "someone@example.com");
+        assert_eq!(claims.name.as_deref(), Some("Some One"));
+    }
+
+    #[tokio::test]
+    async fn unverified_email_is_refused() {
+        for email_verified in [Some(false), None] {
+            let config = provider(|issuer| claims(issuer, email_verified)).await;
+
+            let result = exchange_code(&config, &pending(VERIFIER), CODE).await;
+            assert!(matches!(result, Err(ApiError::Forbidden(_))), "{:?}", result);
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_email_is_refused() {
+        let config = provider(|issuer| {
+            let mut claims = claims(issuer, Some(true));
+            claims.as_object_mut().unwrap().remove("email");
+            claims
+        })
+        .await;
+
+        let result = exchange_code(&config, &pending(VERIFIER), CODE).await;
+        assert!(matches!(result, Err(ApiError::Forbidden(_))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn wrong_code_verifier_is_refused() {
+        let config = provider(|issuer| claims(issuer, Some(true))).await;
+
+        let result = exchange_code(&config, &pending("another-verifier"), CODE).await;
+        assert!(matches!(result, Err(ApiError::Unauthorized(_))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn id_token_for_another_login_is_refused() {
+        let config = provider(|issuer| {
+            let mut claims = claims(issuer, Some(true));
+            claims["nonce"] = json!("another-nonce");
+            claims
+        })
+        .await;
+
+        let result = exchange_code(&config, &pending(VERIFIER), CODE).await;
+        assert!(matches!(result, Err(ApiError::Unauthorized(_))), "{:?}", result);
+    }
+
+    #[sqlx::test]
+    async fn unknown_email_is_provisioned_when_allowed(db: PgPool) {
+        let mut config = provider(|issuer| claims(issuer, Some(true))).await;
+        let (email, claims) = exchange_code(&config, &pending(VERIFIER), CODE).await.unwrap();
+
+        let refused = account_for(&db, 4, &config, &email, &claims).await;
+        assert!(matches!(refused, Err(ApiError::Forbidden(_))), "{:?}", refused);
+
+        config.auto_provision = true;
+        config.default_role = 2;
+        let created = account_for(&db, 4, &config, &email, &claims).await.unwrap();
+        assert_eq!(created.email, "someone@example.com");
+        assert_eq!(created.username, "someone");
+        assert_eq!(created.fullname, "Some One");
+        assert_eq!(created.role, 2);
+
+        let found = account_for(&db, 4, &config, &email, &claims).await.unwrap();
+        assert_eq!(found.id, created.id);
+    }
+}
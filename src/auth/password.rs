@@ -0,0 +1,81 @@
+use std::str::FromStr;
+
+use bcrypt::{hash, verify, BcryptError, HashParts};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+// bcrypt silently truncates anything past 72 bytes
+pub const MAX_PASSWORD_BYTES: usize = 72;
+
+lazy_static! {
+    static ref BCRYPT_HASH: Regex = Regex::new(r"^\$2[abxy]?\$\d{2}\$[./A-Za-z0-9]{53}$").unwrap();
+    static ref HAS_LETTER: Regex = Regex::new(r"[A-Za-z]").unwrap();
+    static ref HAS_DIGIT: Regex = Regex::new(r"[0-9]").unwrap();
+}
+
+pub enum Verification {
+    Valid,
+    // Matched, but the stored value should be replaced with a fresh hash
+    NeedsRehash,
+    Invalid,
+}
+
+pub fn validate_password(password: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(format!(
+            "Password must be at least {} characters long",
+            MIN_PASSWORD_LENGTH
+        ));
+    }
+    if password.len() > MAX_PASSWORD_BYTES {
+        return Err(format!(
+            "Password must be at most {} bytes long",
+            MAX_PASSWORD_BYTES
+        ));
+    }
+    if !HAS_LETTER.is_match(password) || !HAS_DIGIT.is_match(password) {
+        return Err("Password must contain at least one letter and one digit".to_string());
+    }
+    Ok(())
+}
+
+// bcrypt is hundreds of milliseconds of CPU at the usual costs, so it runs off the async workers
+pub async fn hash_password(password: &str, cost: u32) -> Result<String, BcryptError> {
+    let password = password.to_string();
+    tokio::task::spawn_blocking(move || hash(password, cost))
+        .await
+        .map_err(|e| BcryptError::Io(std::io::Error::other(e)))?
+}
+
+pub fn is_hashed(stored: &str) -> bool {
+    BCRYPT_HASH.is_match(stored)
+}
+
+pub async fn verify_password(password: &str, stored: &str, cost: u32) -> Verification {
+    if !is_hashed(stored) {
+        // Legacy rows written before hashing was enforced hold the plaintext
+        if constant_time_eq(password.as_bytes(), stored.as_bytes()) {
+            return Verification::NeedsRehash;
+        }
+        return Verification::Invalid;
+    }
+
+    let (password, hashed) = (password.to_string(), stored.to_string());
+    let matches = tokio::task::spawn_blocking(move || verify(password, &hashed)).await;
+
+    match matches {
+        Ok(Ok(true)) => match HashParts::from_str(stored) {
+            Ok(parts) if parts.get_cost() < cost => Verification::NeedsRehash,
+            _ => Verification::Valid,
+        },
+        _ => Verification::Invalid,
+    }
+}
+
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
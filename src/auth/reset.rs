@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use serde::Deserialize;
+
+use crate::extract::Json;
+use crate::auth::password::{hash_password, validate_password};
+use crate::auth::session::revoke_user_sessions;
+use crate::auth::throttle::{self, Scope};
+use crate::auth::token::{hash_token, random_token};
+use crate::error::ApiError;
+use crate::mailer::Email;
+use crate::AppState;
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ForgotPasswordSchema {
+    pub email: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ResetPasswordSchema {
+    pub token: String,
+    pub password: String,
+}
+
+// Always answers the same way, whether or not the email belongs to an account
+pub async fn forgot_password_handler(
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<ForgotPasswordSchema>,
+) -> Result<impl IntoResponse, ApiError> {
+    let email = body.email.trim().to_lowercase();
+
+    // The lookup and the mail run in the background so response times don't give accounts away
+    tokio::spawn(async move {
+        if let Err(e) = send_reset_email(&data, &email).await {
+            eprintln!("Password reset for {} failed: {}", email, e);
+        }
+    });
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "message": "If an account exists for that email, a reset link has been sent"
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(json_response)))
+}
+
+async fn send_reset_email(data: &AppState, email: &str) -> Result<(), String> {
+    let user = sqlx::query!(
+        "SELECT id, email FROM users WHERE LOWER(email) = $1 AND active != 0",
+        email
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let Some(user) = user else {
+        return Ok(());
+    };
+
+    // One mail per minute at most, so the endpoint can't be used to flood an inbox
+    let recent = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM password_resets WHERE user_id = $1 AND created_at > NOW() - INTERVAL '1 minute') AS "recent!""#,
+        user.id
+    )
+    .fetch_one(&data.db)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if recent {
+        return Ok(());
+    }
+
+    let token = random_token();
+
+    sqlx::query!(
+        "INSERT INTO password_resets (user_id,token_hash,expires_at) VALUES ($1, $2, NOW() + make_interval(secs => $3))",
+        user.id,
+        hash_token(&token),
+        data.config.password_reset.ttl_seconds as f64
+    )
+    .execute(&data.db)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let link = format!("{}?token={}", data.config.password_reset.url, token);
+    let minutes = data.config.password_reset.ttl_seconds / 60;
+
+    data.mailer
+        .send(Email {
+            to: user.email,
+            subject: "Reset your password".to_string(),
+            body: format!(
+                "Someone asked to reset the password for this account.\n\n\
+                Open this link to choose a new one, it expires in {} minutes:\n\n{}\n\n\
+                If it wasn't you, ignore this email and your password stays the same.\n",
+                minutes, link
+            ),
+        })
+        .await
+}
+
+pub async fn reset_password_handler(
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<ResetPasswordSchema>,
+) -> Result<impl IntoResponse, ApiError> {
+    validate_password(&body.password).map_err(ApiError::Validation)?;
+    let password_hash =
+        hash_password(&body.password, data.config.password_cost).await.map_err(ApiError::internal)?;
+
+    let mut tx = data.db.begin().await?;
+
+    // Marking the token used in the same statement that checks it keeps it single-use
+    let user_id = sqlx::query_scalar!(
+        "UPDATE password_resets SET used_at = NOW()
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW() RETURNING user_id",
+        hash_token(body.token.trim())
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::Validation("Reset link is invalid or has expired".to_string()))?;
+
+    let email = sqlx::query_scalar!(
+        "UPDATE users SET password = $1, updated_at = NOW() WHERE id = $2 RETURNING email",
+        password_hash,
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Any other links still in flight stop working too
+    sqlx::query!(
+        "UPDATE password_resets SET used_at = NOW() WHERE user_id = $1 AND used_at IS NULL",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    revoke_user_sessions(&data.db, user_id).await?;
+    throttle::clear(&data.db, Scope::Account, &email.trim().to_lowercase()).await?;
+
+    let json_response = serde_json::json!({
+        "status": "success"
+    });
+
+    Ok((StatusCode::OK, Json(json_response)))
+}
@@ -0,0 +1,144 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Stored in users.role as a smallint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Editor,
+    Admin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    ContentRead,
+    ContentWrite,
+    // Regenerating the translation files and syncing the images directory
+    ContentPublish,
+    UsersManage,
+}
+
+impl Role {
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::Viewer => &[Permission::ContentRead],
+            Role::Editor => &[
+                Permission::ContentRead,
+                Permission::ContentWrite,
+                Permission::ContentPublish,
+            ],
+            Role::Admin => &[
+                Permission::ContentRead,
+                Permission::ContentWrite,
+                Permission::ContentPublish,
+                Permission::UsersManage,
+            ],
+        }
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
+
+impl TryFrom<i16> for Role {
+    type Error = String;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Role::Viewer),
+            2 => Ok(Role::Editor),
+            3 => Ok(Role::Admin),
+            _ => Err(format!("Unknown role {}, expected 1 (viewer), 2 (editor) or 3 (admin)", value)),
+        }
+    }
+}
+
+impl From<Role> for i16 {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Viewer => 1,
+            Role::Editor => 2,
+            Role::Admin => 3,
+        }
+    }
+}
+
+impl Permission {
+    pub const ALL: [Permission; 4] = [
+        Permission::ContentRead,
+        Permission::ContentWrite,
+        Permission::ContentPublish,
+        Permission::UsersManage,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ContentRead => "content:read",
+            Permission::ContentWrite => "content:write",
+            Permission::ContentPublish => "content:publish",
+            Permission::UsersManage => "users:manage",
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Permission::ALL
+            .into_iter()
+            .find(|permission| permission.as_str() == value)
+            .ok_or_else(|| format!("Unknown scope {:?}", value))
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// The authenticated caller, put in the request extensions by `auth_admin`
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub id: Uuid,
+    pub role: Role,
+    // Set when authenticated with an API token, narrows what the role allows
+    pub scopes: Option<Vec<Permission>>,
+}
+
+impl Principal {
+    pub fn can(&self, permission: Permission) -> bool {
+        let in_scope = match &self.scopes {
+            Some(scopes) => scopes.contains(&permission),
+            None => true,
+        };
+        in_scope && self.role.can(permission)
+    }
+}
+
+// Permissions needed to read (GET/HEAD) and to mutate a set of routes
+#[derive(Debug, Clone, Copy)]
+pub struct Access {
+    pub read: Permission,
+    pub write: Permission,
+}
+
+impl Access {
+    pub const CONTENT: Access = Access {
+        read: Permission::ContentRead,
+        write: Permission::ContentWrite,
+    };
+    pub const PUBLISH: Access = Access {
+        read: Permission::ContentPublish,
+        write: Permission::ContentPublish,
+    };
+    pub const USERS: Access = Access {
+        read: Permission::UsersManage,
+        write: Permission::UsersManage,
+    };
+}
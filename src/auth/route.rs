@@ -1,65 +1,114 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::Request,
-    middleware::Next,
-    response::{IntoResponse, Response},
-    routing::{get, post},
-    Json, Router,
+    extract::{Request, State},
+    http::{HeaderMap, Method},
+    middleware::{self, Next},
+    response::Response,
+    routing::{delete, get, post},
+    Router,
 };
-use reqwest::StatusCode;
 use tower_sessions::Session;
 
-use crate::{auth::handler::login_handler, AppState};
+use crate::{
+    auth::{
+        csrf::verify_csrf,
+        handler::{
+            change_password_handler, csrf_handler, delete_all_sessions_handler,
+            delete_session_handler, login_handler, logout_handler, me_handler,
+            session_list_handler, update_me_handler,
+        },
+        oidc::{oidc_callback_handler, oidc_login_handler},
+        reset::{forgot_password_handler, reset_password_handler},
+        role::{Access, Principal, Role},
+        session::validate_session,
+        token::{bearer_token, validate_token},
+        two_factor::{
+            disable_two_factor_handler, enable_two_factor_handler, recovery_codes_handler,
+            setup_two_factor_handler, verify_two_factor_handler,
+        },
+    },
+    error::ApiError,
+    AppState,
+};
 
-use super::handler::{logout_handler, test_login_handler};
+// A bearer token takes precedence over the session cookie. Browsers never attach it on their
+// own, so only cookie requests need the CSRF check
+async fn resolve_principal(
+    data: &AppState,
+    session: &Session,
+    method: &Method,
+    headers: &HeaderMap,
+) -> Result<Principal, ApiError> {
+    match bearer_token(headers) {
+        Some(token) => validate_token(&data.db, token).await,
+        None => {
+            let principal = validate_session(session, &data.db).await?;
+            verify_csrf(session, method, headers).await?;
+            Ok(principal)
+        }
+    }
+}
 
+// Lets in any signed in user, admins who still have to enroll in 2FA included
 pub async fn authenticate(
+    State(data): State<Arc<AppState>>,
     session: Session,
-    request: Request,
+    mut request: Request,
     next: Next,
-) -> Result<Response, (StatusCode, impl IntoResponse)> {
-    // Check if the user is logged in
-    if let Ok(Some(_logged_in)) = session.get::<usize>("logged_in").await {
-        // If logged in, continue processing the request
-        let response = next.run(request).await;
-        Ok(response)
-    } else {
-        let error_response = serde_json::json!({
-            "error": "Unauthorized",
-        });
-        Err((StatusCode::UNAUTHORIZED, Json(error_response)))
-    }
+) -> Result<Response, ApiError> {
+    let principal = resolve_principal(&data, &session, request.method(), request.headers()).await?;
+
+    request.extensions_mut().insert(principal);
+
+    let response = next.run(request).await;
+    Ok(response)
 }
 
 pub async fn auth_admin(
+    State(data): State<Arc<AppState>>,
     session: Session,
-    request: Request,
+    mut request: Request,
     next: Next,
-) -> Result<Response, (StatusCode, impl IntoResponse)> {
-    // Check if the user is logged in
-    if let Ok(Some(_logged_in)) = session.get::<usize>("logged_in").await {
-        
-    } else {
-        let error_response = serde_json::json!({
-            "error": "Unauthorized",
-        });
-        return Err((StatusCode::UNAUTHORIZED, Json(error_response)));
-    }
+) -> Result<Response, ApiError> {
+    let principal = resolve_principal(&data, &session, request.method(), request.headers()).await?;
 
-    if let Ok(Some(role)) = session.get::<usize>("role").await {
-        if role != 3 {
-            let error_response = serde_json::json!({
-                "error": "Unauthorized",
-            });
-            return Err((StatusCode::UNAUTHORIZED, Json(error_response)));
+    if data.config.two_factor.require_for_admins && principal.role == Role::Admin {
+        let enabled = sqlx::query_scalar!("SELECT totp_enabled FROM users WHERE id = $1", principal.id)
+            .fetch_one(&data.db)
+            .await?;
+        if !enabled {
+            return Err(ApiError::Forbidden(
+                "Admin accounts must enable two-factor authentication, start at /auth/2fa/setup"
+                    .to_string(),
+            ));
         }
-    } else {
-        let error_response = serde_json::json!({
-            "error": "Session expired relogin",
-        });
+    }
 
-        return Err((StatusCode::UNAUTHORIZED, Json(error_response)));
+    request.extensions_mut().insert(principal);
+
+    let response = next.run(request).await;
+    Ok(response)
+}
+
+// Route layer for routers nested behind `auth_admin`
+pub async fn require_permission(
+    State(access): State<Access>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let principal = request
+        .extensions()
+        .get::<Principal>()
+        .ok_or_else(|| ApiError::Unauthorized("Unauthorized".to_string()))?;
+
+    let permission = match *request.method() {
+        Method::GET | Method::HEAD => access.read,
+        _ => access.write,
+    };
+
+    if !principal.can(permission) {
+        return Err(ApiError::Forbidden(format!("Missing permission {}", permission)));
     }
 
     let response = next.run(request).await;
@@ -67,9 +116,36 @@ pub async fn auth_admin(
 }
 
 pub fn auth_router(app_state: Arc<AppState>) -> Router {
-    Router::new()
+    // Same authentication and CSRF rules as the admin routes, minus the 2FA requirement so
+    // admins can still enroll
+    let account_router = Router::new()
+        .route("/auth/me", get(me_handler).patch(update_me_handler))
+        .route("/auth/password", post(change_password_handler))
+        .route("/auth/2fa/setup", post(setup_two_factor_handler))
+        .route("/auth/2fa/enable", post(enable_two_factor_handler))
+        .route("/auth/2fa/disable", post(disable_two_factor_handler))
+        .route("/auth/2fa/recovery-codes", post(recovery_codes_handler))
+        .route("/auth/sessions", get(session_list_handler).delete(delete_all_sessions_handler))
+        .route("/auth/sessions/:id", delete(delete_session_handler))
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), authenticate));
+
+    let router = Router::new()
+        .merge(account_router)
         .route("/auth/login", post(login_handler))
-        .route("/auth/test_login", get(test_login_handler))
         .route("/auth/logout", get(logout_handler))
-        .with_state(app_state)
+        .route("/auth/csrf", get(csrf_handler))
+        .route("/auth/forgot", post(forgot_password_handler))
+        .route("/auth/reset", post(reset_password_handler))
+        .route("/auth/2fa/verify", post(verify_two_factor_handler))
+        .route("/auth/oidc/login", get(oidc_login_handler))
+        .route("/auth/oidc/callback", get(oidc_callback_handler));
+
+    // Only compiled into builds made with `--features dev-impersonation`
+    #[cfg(feature = "dev-impersonation")]
+    let router = router.route(
+        "/auth/impersonate/:id",
+        post(super::impersonate::impersonate_handler),
+    );
+
+    router.with_state(app_state)
 }
@@ -8,22 +8,46 @@ pub struct Login {
     pub password: Option<String>,
 }
 
-#[derive(Default, Deserialize, Serialize)]
-pub struct Logged(bool);
-
 #[derive(Debug, Default, FromRow, Deserialize, Serialize)]
 pub struct User {
     pub id: Uuid,
     pub email: String,
     pub username: String,
+    #[serde(skip_serializing)]
     pub password: String,
     pub fullname: String,
     pub role: i16,
     pub avatar: String,
     pub active: i16,
+    pub totp_enabled: bool,
 }
 
-#[derive(Debug, Default, FromRow, Deserialize, Serialize)]
-pub struct NewUser {
-    pub id: Uuid
-}
\ No newline at end of file
+#[derive(Deserialize, Debug, Default)]
+pub struct UpdateProfileSchema {
+    pub username: Option<String>,
+    pub fullname: Option<String>,
+    pub avatar: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ChangePasswordSchema {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct TwoFactorCodeSchema {
+    pub code: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct DisableTwoFactorSchema {
+    pub password: String,
+}
+
+// Second login step, one of the two must be set
+#[derive(Deserialize, Debug, Default)]
+pub struct VerifyTwoFactorSchema {
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
@@ -0,0 +1,201 @@
+use sqlx::{Pool, Postgres};
+use tower_sessions::Session;
+use uuid::Uuid;
+
+use crate::{
+    auth::{
+        client::ClientInfo,
+        csrf::issue_csrf_token,
+        role::{Principal, Role},
+        schema::User,
+    },
+    error::ApiError,
+};
+
+// Starts a fresh session for a user who just proved who they are, returns its CSRF token
+pub async fn start_session(
+    session: &Session,
+    db: &Pool<Postgres>,
+    item: &User,
+    client: &ClientInfo,
+) -> Result<String, ApiError> {
+    // New id on login so a session fixed before authentication is never promoted
+    session.cycle_id().await.map_err(ApiError::internal)?;
+    store_user(session, item).await.map_err(ApiError::internal)?;
+
+    register_session(session, db, item.id, client).await
+}
+
+// Moves an authenticated session to a new id, e.g. after a password change
+pub async fn rotate_session(
+    session: &Session,
+    db: &Pool<Postgres>,
+    user_id: Uuid,
+    client: &ClientInfo,
+) -> Result<String, ApiError> {
+    // The old user_sessions row goes with the old id through ON DELETE CASCADE
+    session.cycle_id().await.map_err(ApiError::internal)?;
+
+    register_session(session, db, user_id, client).await
+}
+
+async fn register_session(
+    session: &Session,
+    db: &Pool<Postgres>,
+    user_id: Uuid,
+    client: &ClientInfo,
+) -> Result<String, ApiError> {
+    let csrf_token = issue_csrf_token(session).await?;
+    session.save().await.map_err(ApiError::internal)?;
+
+    let session_id = session
+        .id()
+        .ok_or_else(|| ApiError::internal("session has no id after save"))?;
+
+    sqlx::query!(
+        "INSERT INTO user_sessions (session_id,user_id,ip,user_agent) VALUES ($1, $2, $3, $4) ON CONFLICT (session_id) DO NOTHING",
+        session_id.to_string(),
+        user_id,
+        client.ip.to_string(),
+        client.user_agent
+    )
+    .execute(db)
+    .await?;
+
+    Ok(csrf_token)
+}
+
+// Only what validate_session needs, the profile is always read from the database
+async fn store_user(session: &Session, item: &User) -> Result<(), tower_sessions::session::Error> {
+    session.insert("logged_in",1).await?;
+    session.insert("id",item.id).await?;
+    session.insert("role",item.role).await?;
+    Ok(())
+}
+
+const TWO_FACTOR_USER: &str = "two_factor_user";
+const TWO_FACTOR_STARTED: &str = "two_factor_started";
+// How long a login may wait for its second factor
+const TWO_FACTOR_TIMEOUT_SECONDS: i64 = 300;
+
+// The password was right but a second factor is still missing. The session stays
+// unauthenticated, it only remembers whose code to expect
+pub async fn start_two_factor(session: &Session, user_id: Uuid) -> Result<(), ApiError> {
+    session.clear().await;
+    session.cycle_id().await.map_err(ApiError::internal)?;
+    session
+        .insert(TWO_FACTOR_USER, user_id)
+        .await
+        .map_err(ApiError::internal)?;
+    session
+        .insert(TWO_FACTOR_STARTED, chrono::Utc::now().timestamp())
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(())
+}
+
+pub async fn pending_two_factor_user(session: &Session) -> Result<Uuid, ApiError> {
+    let (Ok(Some(user_id)), Ok(Some(started))) = (
+        session.get::<Uuid>(TWO_FACTOR_USER).await,
+        session.get::<i64>(TWO_FACTOR_STARTED).await,
+    ) else {
+        return Err(ApiError::Unauthorized("No login is waiting for a second factor".to_string()));
+    };
+
+    if chrono::Utc::now().timestamp() - started > TWO_FACTOR_TIMEOUT_SECONDS {
+        session.clear().await;
+        return Err(ApiError::Unauthorized("Two-factor login timed out, log in again".to_string()));
+    }
+
+    Ok(user_id)
+}
+
+pub async fn clear_two_factor(session: &Session) -> Result<(), ApiError> {
+    session
+        .remove_value(TWO_FACTOR_USER)
+        .await
+        .map_err(ApiError::internal)?;
+    session
+        .remove_value(TWO_FACTOR_STARTED)
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(())
+}
+
+// Checks the session against the users row, so deactivations and role changes apply immediately
+pub async fn validate_session(session: &Session, db: &Pool<Postgres>) -> Result<Principal, ApiError> {
+    if !matches!(session.get::<usize>("logged_in").await, Ok(Some(_))) {
+        if let Ok(Some(_)) = session.get::<Uuid>(TWO_FACTOR_USER).await {
+            return Err(ApiError::Unauthorized("Two-factor code required".to_string()));
+        }
+        return Err(ApiError::Unauthorized("Unauthorized".to_string()));
+    }
+
+    let (Ok(Some(id)), Ok(Some(session_role))) = (
+        session.get::<Uuid>("id").await,
+        session.get::<i16>("role").await,
+    ) else {
+        return Err(ApiError::Unauthorized("Session expired relogin".to_string()));
+    };
+
+    let user = sqlx::query!("SELECT role, active FROM users WHERE id = $1", id)
+        .fetch_optional(db)
+        .await?;
+
+    let Some(user) = user.filter(|user| user.active != 0) else {
+        let _ = session.flush().await;
+        return Err(ApiError::Unauthorized("Account is disabled".to_string()));
+    };
+
+    if user.role != session_role {
+        let _ = session.flush().await;
+        return Err(ApiError::Unauthorized("Session expired relogin".to_string()));
+    }
+
+    let role = Role::try_from(user.role).map_err(|_| ApiError::Forbidden("Forbidden".to_string()))?;
+
+    // At most one write a minute per session
+    if let Some(session_id) = session.id() {
+        sqlx::query!(
+            "UPDATE user_sessions SET last_seen_at = NOW() WHERE session_id = $1 AND last_seen_at < NOW() - INTERVAL '1 minute'",
+            session_id.to_string()
+        )
+        .execute(db)
+        .await?;
+    }
+
+    Ok(Principal {
+        id,
+        role,
+        scopes: None,
+    })
+}
+
+pub async fn revoke_user_sessions(db: &Pool<Postgres>, user_id: Uuid) -> Result<u64, ApiError> {
+    // user_sessions rows go with the session through ON DELETE CASCADE
+    let rows_affected = sqlx::query!(
+        "DELETE FROM tower_sessions.session WHERE id IN (SELECT session_id FROM user_sessions WHERE user_id = $1)",
+        user_id
+    )
+    .execute(db)
+    .await?
+    .rows_affected();
+
+    Ok(rows_affected)
+}
+
+// Signs the user out everywhere except the given session
+pub async fn revoke_other_sessions(db: &Pool<Postgres>, user_id: Uuid, keep: &Session) -> Result<u64, ApiError> {
+    let keep_id = keep.id().map(|id| id.to_string()).unwrap_or_default();
+
+    let rows_affected = sqlx::query!(
+        "DELETE FROM tower_sessions.session WHERE id IN (SELECT session_id FROM user_sessions WHERE user_id = $1 AND session_id <> $2)",
+        user_id,
+        keep_id
+    )
+    .execute(db)
+    .await?
+    .rows_affected();
+
+    Ok(rows_affected)
+}
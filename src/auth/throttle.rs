@@ -0,0 +1,133 @@
+use sqlx::{Pool, Postgres};
+
+use crate::{config::LoginConfig, error::ApiError};
+
+#[derive(Clone, Copy)]
+pub enum Scope {
+    Ip,
+    Account,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Ip => "ip",
+            Scope::Account => "account",
+        }
+    }
+}
+
+// Fails with TooManyRequests while the subject is locked out
+pub async fn check(db: &Pool<Postgres>, scope: Scope, subject: &str) -> Result<(), ApiError> {
+    let remaining = sqlx::query_scalar!(
+        r#"SELECT CEIL(EXTRACT(EPOCH FROM locked_until - NOW()))::bigint AS "remaining!" FROM login_throttles WHERE scope = $1 AND subject = $2 AND locked_until > NOW()"#,
+        scope.as_str(),
+        subject
+    )
+    .fetch_optional(db)
+    .await?;
+
+    match remaining {
+        Some(seconds) => Err(ApiError::TooManyRequests(seconds)),
+        None => Ok(()),
+    }
+}
+
+pub async fn record_failure(
+    db: &Pool<Postgres>,
+    config: &LoginConfig,
+    scope: Scope,
+    subject: &str,
+) -> Result<(), ApiError> {
+    // Failures outside the window start a new streak
+    let failures = sqlx::query_scalar!(
+        "INSERT INTO login_throttles (scope,subject,failures,last_failure_at) VALUES ($1, $2, 1, NOW())
+        ON CONFLICT (scope, subject) DO UPDATE SET
+            failures = CASE WHEN login_throttles.last_failure_at < NOW() - make_interval(secs => $3)
+                THEN 1 ELSE login_throttles.failures + 1 END,
+            last_failure_at = NOW()
+        RETURNING failures",
+        scope.as_str(),
+        subject,
+        config.window_seconds as f64
+    )
+    .fetch_one(db)
+    .await?;
+
+    let delay = config.delay(failures);
+    if delay > 0 {
+        sqlx::query!(
+            "UPDATE login_throttles SET locked_until = NOW() + make_interval(secs => $1) WHERE scope = $2 AND subject = $3",
+            delay as f64,
+            scope.as_str(),
+            subject
+        )
+        .execute(db)
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn clear(db: &Pool<Postgres>, scope: Scope, subject: &str) -> Result<(), ApiError> {
+    sqlx::query!(
+        "DELETE FROM login_throttles WHERE scope = $1 AND subject = $2",
+        scope.as_str(),
+        subject
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::LoginConfig;
+
+    #[test]
+    fn free_attempts_have_no_delay() {
+        let config = LoginConfig::default();
+        assert_eq!(config.delay(0), 0);
+        assert_eq!(config.delay(1), 0);
+        assert_eq!(config.delay(config.free_attempts - 1), 0);
+    }
+
+    #[test]
+    fn delay_doubles_with_every_failure() {
+        let config = LoginConfig::default();
+        let delays: Vec<i64> = (3..10).map(|failures| config.delay(failures)).collect();
+        assert_eq!(delays, [2, 4, 8, 16, 32, 64, 128]);
+    }
+
+    #[test]
+    fn max_attempts_lock_out() {
+        let config = LoginConfig::default();
+        assert_eq!(config.delay(config.max_attempts), config.lockout_seconds);
+        assert_eq!(config.delay(config.max_attempts + 50), config.lockout_seconds);
+    }
+
+    #[test]
+    fn delay_never_exceeds_the_lockout() {
+        let config = LoginConfig {
+            lockout_seconds: 10,
+            ..LoginConfig::default()
+        };
+        assert_eq!(config.delay(5), 8);
+        assert_eq!(config.delay(6), 10);
+        assert_eq!(config.delay(9), 10);
+    }
+
+    #[test]
+    fn long_streaks_do_not_overflow() {
+        let config = LoginConfig {
+            free_attempts: 1,
+            base_delay_seconds: i64::MAX / 2,
+            max_attempts: i32::MAX,
+            lockout_seconds: i64::MAX,
+            window_seconds: 900,
+        };
+        assert_eq!(config.delay(1), i64::MAX / 2);
+        assert_eq!(config.delay(1_000), i64::MAX);
+    }
+}
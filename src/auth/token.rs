@@ -0,0 +1,73 @@
+use axum::http::{header::AUTHORIZATION, HeaderMap};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    auth::role::{Permission, Principal, Role},
+    error::ApiError,
+};
+
+pub const TOKEN_PREFIX: &str = "pat_";
+// Characters of the token kept in plain text to identify it
+const VISIBLE_LENGTH: usize = 12;
+
+// 256 random bits, hex encoded
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+// Returns the token to hand out once and its prefix
+pub fn generate_token() -> (String, String) {
+    let token = format!("{}{}", TOKEN_PREFIX, random_token());
+    let prefix = token[..VISIBLE_LENGTH].to_string();
+    (token, prefix)
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+// Resolves a bearer token to its owner, checked against the users row like sessions are
+pub async fn validate_token(db: &Pool<Postgres>, token: &str) -> Result<Principal, ApiError> {
+    let item = sqlx::query!(
+        r#"SELECT t.id, t.user_id, t.scopes, t.expires_at < NOW() AS "expired!", u.role, u.active
+        FROM api_tokens t JOIN users u ON u.id = t.user_id WHERE t.token_hash = $1"#,
+        hash_token(token)
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ApiError::Unauthorized("Invalid token".to_string()))?;
+
+    if item.expired {
+        return Err(ApiError::Unauthorized("Token expired".to_string()));
+    }
+    if item.active == 0 {
+        return Err(ApiError::Unauthorized("Account is disabled".to_string()));
+    }
+
+    let role = Role::try_from(item.role).map_err(|_| ApiError::Forbidden("Forbidden".to_string()))?;
+    // Scopes are validated on creation, anything unknown is dropped
+    let scopes: Vec<Permission> = item.scopes.iter().filter_map(|scope| scope.parse().ok()).collect();
+
+    let _ = sqlx::query!("UPDATE api_tokens SET last_used_at = NOW() WHERE id = $1", item.id)
+        .execute(db)
+        .await;
+
+    Ok(Principal {
+        id: item.user_id,
+        role,
+        scopes: Some(scopes),
+    })
+}
@@ -0,0 +1,141 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::auth::{password::constant_time_eq, token::random_token};
+
+const DIGITS: usize = 6;
+const STEP_SECONDS: u64 = 30;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+// Base32 encoded, the form authenticator apps and the otpauth URI expect
+pub fn new_secret() -> String {
+    match Secret::generate_secret().to_encoded() {
+        Secret::Encoded(secret) => secret,
+        Secret::Raw(_) => unreachable!("to_encoded always returns an encoded secret"),
+    }
+}
+
+fn totp(secret: &str, issuer: &str, account: &str) -> Result<TOTP, String> {
+    let bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| format!("{:?}", e))?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        DIGITS,
+        1,
+        STEP_SECONDS,
+        bytes,
+        Some(issuer.to_string()),
+        account.to_string(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+pub fn otpauth_uri(secret: &str, issuer: &str, account: &str) -> Result<String, String> {
+    Ok(totp(secret, issuer, account)?.get_url())
+}
+
+// Returns the time step the code belongs to, allowing one step of clock drift either way
+pub fn verify_code(secret: &str, code: &str) -> Option<i64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    verify_code_at(secret, code, now)
+}
+
+fn verify_code_at(secret: &str, code: &str, now: u64) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS {
+        return None;
+    }
+
+    let totp = totp(secret, "-", "-").ok()?;
+    let current = now / STEP_SECONDS;
+
+    [current - 1, current, current + 1]
+        .into_iter()
+        .find(|step| constant_time_eq(totp.generate(step * STEP_SECONDS).as_bytes(), code.as_bytes()))
+        .map(|step| step as i64)
+}
+
+// Codes look like "1a2b3-c4d5e", only their hashes are stored
+pub fn new_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let token = random_token();
+            format!("{}-{}", &token[..5], &token[5..10])
+        })
+        .collect()
+}
+
+// Users may type codes in any case, with or without the dash
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The RFC 6238 SHA1 seed "12345678901234567890" in base32
+    const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn generates_the_rfc_6238_codes() {
+        let totp = totp(SECRET, "-", "-").unwrap();
+        // Appendix B, truncated to six digits
+        let vectors = [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ];
+        for (time, code) in vectors {
+            assert_eq!(totp.generate(time), code, "at {}", time);
+        }
+    }
+
+    #[test]
+    fn accepts_one_step_of_drift_either_way() {
+        let now = 1111111111;
+        let current = (now / STEP_SECONDS) as i64;
+
+        assert_eq!(verify_code_at(SECRET, "050471", now), Some(current));
+        assert_eq!(verify_code_at(SECRET, "050471", now - STEP_SECONDS), Some(current));
+        assert_eq!(verify_code_at(SECRET, "050471", now + STEP_SECONDS), Some(current));
+        assert_eq!(verify_code_at(SECRET, "050471", now - 2 * STEP_SECONDS), None);
+        assert_eq!(verify_code_at(SECRET, "050471", now + 2 * STEP_SECONDS), None);
+    }
+
+    #[test]
+    fn rejects_malformed_codes() {
+        let now = 1111111111;
+        assert_eq!(verify_code_at(SECRET, " 050471 ", now), Some((now / STEP_SECONDS) as i64));
+        assert_eq!(verify_code_at(SECRET, "05047", now), None);
+        assert_eq!(verify_code_at(SECRET, "0504711", now), None);
+        assert_eq!(verify_code_at(SECRET, "000000", now), None);
+        assert_eq!(verify_code_at("not base32!", "050471", now), None);
+    }
+
+    #[test]
+    fn recovery_codes_are_distinct_and_normalize() {
+        let codes = new_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        for code in &codes {
+            assert_eq!(code.len(), 11);
+            assert_eq!(&code[5..6], "-");
+        }
+        let mut unique = codes.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), codes.len());
+
+        assert_eq!(normalize_recovery_code(" 1A2B3-c4D5e "), "1a2b3c4d5e");
+        assert_eq!(normalize_recovery_code("1a2b3c4d5e"), "1a2b3c4d5e");
+    }
+}
@@ -0,0 +1,323 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+    Extension,
+};
+use sqlx::PgPool;
+use tower_sessions::Session;
+use uuid::Uuid;
+
+use crate::extract::Json;
+use crate::auth::client::ClientInfo;
+use crate::auth::model::ProfileModel;
+use crate::auth::password::{verify_password, Verification};
+use crate::auth::role::{Principal, Role};
+use crate::auth::schema::{DisableTwoFactorSchema, TwoFactorCodeSchema, User, VerifyTwoFactorSchema};
+use crate::auth::session::{clear_two_factor, pending_two_factor_user, start_session};
+use crate::auth::throttle::{self, Scope};
+use crate::auth::token::hash_token;
+use crate::auth::totp::{new_recovery_codes, new_secret, normalize_recovery_code, otpauth_uri, verify_code};
+use crate::error::ApiError;
+use crate::AppState;
+
+// Enrollment changes what a login needs, so it takes a real session and not an API token
+fn require_session(principal: &Principal) -> Result<(), ApiError> {
+    if principal.scopes.is_some() {
+        return Err(ApiError::Forbidden("Log in to manage two-factor authentication".to_string()));
+    }
+    Ok(())
+}
+
+// Accepts the code once per time step, so a code seen over someone's shoulder can't be reused
+async fn accept_code(data: &AppState, user_id: Uuid, secret: &str, code: &str) -> Result<bool, ApiError> {
+    let Some(step) = verify_code(secret, code) else {
+        return Ok(false);
+    };
+
+    let rows_affected = sqlx::query!(
+        "UPDATE users SET totp_last_step = $1 WHERE id = $2 AND (totp_last_step IS NULL OR totp_last_step < $1)",
+        step,
+        user_id
+    )
+    .execute(&data.db)
+    .await?
+    .rows_affected();
+
+    Ok(rows_affected == 1)
+}
+
+// Marks the code used in the same statement that checks it, so it is accepted only once
+async fn use_recovery_code(db: &PgPool, user_id: Uuid, code: &str) -> Result<bool, ApiError> {
+    let rows_affected = sqlx::query!(
+        "UPDATE recovery_codes SET used_at = NOW() WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL",
+        user_id,
+        hash_token(&normalize_recovery_code(code))
+    )
+    .execute(db)
+    .await?
+    .rows_affected();
+
+    Ok(rows_affected == 1)
+}
+
+async fn replace_recovery_codes(data: &AppState, user_id: Uuid) -> Result<Vec<String>, ApiError> {
+    let codes = new_recovery_codes();
+    let hashes: Vec<String> = codes
+        .iter()
+        .map(|code| hash_token(&normalize_recovery_code(code)))
+        .collect();
+
+    let mut tx = data.db.begin().await?;
+
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        "INSERT INTO recovery_codes (user_id,code_hash) SELECT $1, UNNEST($2::text[])",
+        user_id,
+        &hashes
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(codes)
+}
+
+// Starts enrollment with a fresh secret, 2FA is only switched on by /auth/2fa/enable
+pub async fn setup_two_factor_handler(
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_session(&principal)?;
+
+    let user = sqlx::query!("SELECT email, totp_enabled FROM users WHERE id = $1", principal.id)
+        .fetch_optional(&data.db)
+        .await?
+        .ok_or_else(|| ApiError::not_found(principal.id))?;
+
+    if user.totp_enabled {
+        return Err(ApiError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+
+    let secret = new_secret();
+    let uri = otpauth_uri(&secret, &data.config.two_factor.issuer, &user.email).map_err(ApiError::internal)?;
+
+    sqlx::query!(
+        "UPDATE users SET totp_secret = $1, totp_last_step = NULL WHERE id = $2",
+        secret,
+        principal.id
+    )
+    .execute(&data.db)
+    .await?;
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "data": {
+            "secret": secret,
+            "otpauth_uri": uri
+        }
+    });
+
+    Ok(Json(json_response))
+}
+
+pub async fn enable_two_factor_handler(
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<TwoFactorCodeSchema>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_session(&principal)?;
+
+    let user = sqlx::query!("SELECT totp_secret, totp_enabled FROM users WHERE id = $1", principal.id)
+        .fetch_optional(&data.db)
+        .await?
+        .ok_or_else(|| ApiError::not_found(principal.id))?;
+
+    if user.totp_enabled {
+        return Err(ApiError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+    let secret = user
+        .totp_secret
+        .ok_or_else(|| ApiError::Validation("Start with /auth/2fa/setup".to_string()))?;
+
+    if !accept_code(&data, principal.id, &secret, &body.code).await? {
+        return Err(ApiError::Validation("Invalid two-factor code".to_string()));
+    }
+
+    sqlx::query!("UPDATE users SET totp_enabled = TRUE WHERE id = $1", principal.id)
+        .execute(&data.db)
+        .await?;
+
+    let recovery_codes = replace_recovery_codes(&data, principal.id).await?;
+
+    // The recovery codes are only ever shown here
+    let json_response = serde_json::json!({
+        "status": "success",
+        "data": {
+            "recovery_codes": recovery_codes
+        }
+    });
+
+    Ok(Json(json_response))
+}
+
+pub async fn recovery_codes_handler(
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<TwoFactorCodeSchema>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_session(&principal)?;
+
+    let user = sqlx::query!("SELECT totp_secret, totp_enabled FROM users WHERE id = $1", principal.id)
+        .fetch_optional(&data.db)
+        .await?
+        .ok_or_else(|| ApiError::not_found(principal.id))?;
+
+    let secret = match (user.totp_enabled, user.totp_secret) {
+        (true, Some(secret)) => secret,
+        _ => return Err(ApiError::Validation("Two-factor authentication is not enabled".to_string())),
+    };
+
+    if !accept_code(&data, principal.id, &secret, &body.code).await? {
+        return Err(ApiError::Validation("Invalid two-factor code".to_string()));
+    }
+
+    let recovery_codes = replace_recovery_codes(&data, principal.id).await?;
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "data": {
+            "recovery_codes": recovery_codes
+        }
+    });
+
+    Ok(Json(json_response))
+}
+
+pub async fn disable_two_factor_handler(
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<DisableTwoFactorSchema>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_session(&principal)?;
+
+    if data.config.two_factor.require_for_admins && principal.role == Role::Admin {
+        return Err(ApiError::Forbidden(
+            "Two-factor authentication is required for admin accounts".to_string(),
+        ));
+    }
+
+    let user = sqlx::query!("SELECT email, password FROM users WHERE id = $1", principal.id)
+        .fetch_optional(&data.db)
+        .await?
+        .ok_or_else(|| ApiError::not_found(principal.id))?;
+
+    let account = user.email.trim().to_lowercase();
+    throttle::check(&data.db, Scope::Account, &account).await?;
+
+    if let Verification::Invalid = verify_password(&body.password, &user.password, data.config.password_cost).await {
+        throttle::record_failure(&data.db, &data.config.login, Scope::Account, &account).await?;
+        return Err(ApiError::Validation("Current password is incorrect".to_string()));
+    }
+
+    sqlx::query!(
+        "UPDATE users SET totp_enabled = FALSE, totp_secret = NULL, totp_last_step = NULL WHERE id = $1",
+        principal.id
+    )
+    .execute(&data.db)
+    .await?;
+
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", principal.id)
+        .execute(&data.db)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Second login step, finishes the session started by login_handler
+pub async fn verify_two_factor_handler(
+    session: Session,
+    client: ClientInfo,
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<VerifyTwoFactorSchema>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = pending_two_factor_user(&session).await?;
+
+    let item = sqlx::query_as!(
+        User,
+        "SELECT id,username,email,password,fullname,role,avatar,active,totp_enabled FROM users WHERE id=$1",
+        user_id
+    )
+    .fetch_optional(&data.db)
+    .await?
+    .filter(|item| item.active != 0)
+    .ok_or_else(|| ApiError::Unauthorized("Account is disabled".to_string()))?;
+
+    let account = item.email.trim().to_lowercase();
+    throttle::check(&data.db, Scope::Account, &account).await?;
+
+    let secret = sqlx::query_scalar!("SELECT totp_secret FROM users WHERE id = $1", user_id)
+        .fetch_one(&data.db)
+        .await?
+        .unwrap_or_default();
+
+    let accepted = match (&body.code, &body.recovery_code) {
+        (Some(code), _) => accept_code(&data, user_id, &secret, code).await?,
+        (None, Some(recovery_code)) => use_recovery_code(&data.db, user_id, recovery_code).await?,
+        (None, None) => {
+            return Err(ApiError::Validation("code or recovery_code is required".to_string()));
+        }
+    };
+
+    if !accepted {
+        throttle::record_failure(&data.db, &data.config.login, Scope::Account, &account).await?;
+        return Err(ApiError::Unauthorized("Invalid two-factor code".to_string()));
+    }
+
+    throttle::clear(&data.db, Scope::Account, &account).await?;
+    clear_two_factor(&session).await?;
+
+    let csrf_token = start_session(&session, &data.db, &item, &client).await?;
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "data": ProfileModel::from(&item),
+        "csrf_token": csrf_token
+    });
+
+    Ok((StatusCode::OK, Json(json_response)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test]
+    async fn recovery_codes_work_once(db: PgPool) {
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users (username,password,email,fullname) VALUES ('a', '-', 'a@x.com', 'A') RETURNING id"
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO recovery_codes (user_id,code_hash) VALUES ($1, $2)",
+            user_id,
+            hash_token("1a2b3c4d5e")
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        assert!(!use_recovery_code(&db, user_id, "00000-00000").await.unwrap());
+        assert!(!use_recovery_code(&db, Uuid::new_v4(), "1a2b3-c4d5e").await.unwrap());
+        assert!(use_recovery_code(&db, user_id, "1A2B3-C4D5E").await.unwrap());
+        assert!(!use_recovery_code(&db, user_id, "1a2b3-c4d5e").await.unwrap());
+    }
+}
@@ -0,0 +1,752 @@
+use std::{fmt, net::SocketAddr, path::Path, str::FromStr};
+
+use axum::http::HeaderValue;
+use serde::Deserialize;
+use tower_sessions::cookie::SameSite;
+
+use crate::auth::role::Role;
+use crate::storage::store::validate_key;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub bind_address: String,
+    pub allowed_origins: Vec<String>,
+    pub upload_dir: String,
+    pub password_cost: u32,
+    // Take the client address from X-Forwarded-For, only safe behind a reverse proxy
+    pub trust_proxy: bool,
+    // Proxies in front of the server, each appends one X-Forwarded-For entry
+    pub trusted_proxy_hops: usize,
+    pub database: DatabaseConfig,
+    pub session: SessionConfig,
+    pub pagination: PaginationConfig,
+    pub login: LoginConfig,
+    pub mail: MailConfig,
+    pub password_reset: PasswordResetConfig,
+    pub two_factor: TwoFactorConfig,
+    pub oidc: OidcConfig,
+    pub images: ImagesConfig,
+    pub storage: StorageConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    pub expiry_seconds: i64,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PaginationConfig {
+    pub default_limit: usize,
+    pub max_limit: usize,
+    pub public_default_limit: usize,
+    pub public_max_limit: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoginConfig {
+    // Failures allowed before any delay kicks in
+    pub free_attempts: i32,
+    pub base_delay_seconds: i64,
+    // Failures after which the full lockout applies
+    pub max_attempts: i32,
+    pub lockout_seconds: i64,
+    // Failures older than this are forgotten
+    pub window_seconds: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MailConfig {
+    // stdout, file or smtp
+    pub transport: String,
+    pub from: String,
+    // Where the file transport writes one .eml file per message
+    pub dir: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: String,
+    pub smtp_password: String,
+    // starttls, tls or none
+    pub smtp_tls: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PasswordResetConfig {
+    // Frontend page the emailed link points to, the token is appended as ?token=
+    pub url: String,
+    pub ttl_seconds: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TwoFactorConfig {
+    // Shown next to the account in authenticator apps
+    pub issuer: String,
+    // Admins without 2FA can only reach /auth/me and /auth/2fa/* until they enroll
+    pub require_for_admins: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OidcConfig {
+    pub enabled: bool,
+    // Base URL of the provider, its /.well-known/openid-configuration is read on every login
+    pub issuer: String,
+    pub client_id: String,
+    // Left empty for public clients, PKCE is used either way
+    pub client_secret: String,
+    // Must be registered with the provider and lead to GET /auth/oidc/callback
+    pub redirect_uri: String,
+    // Space separated, must include openid and email
+    pub scopes: String,
+    // Create an account for an unknown email instead of refusing the login
+    pub auto_provision: bool,
+    // Role given to created accounts, 1 (viewer), 2 (editor) or 3 (admin)
+    pub default_role: i16,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ImagesConfig {
+    // Uploads larger than this on either side are scaled down to fit
+    pub max_dimension: u32,
+    // A scaled down copy is stored for each width smaller than the upload
+    pub variant_widths: Vec<u32>,
+    // Lossy WebP quality of stored uploads and their variants, 1 to 100
+    pub webp_quality: u8,
+    // Cache of the copies resized on request by /images/:path?w=&h=
+    pub derivative_dir: String,
+    // Requested sizes are rounded up to a multiple of this, which bounds how many copies one image can have
+    pub derivative_step: u32,
+    // Requested qualities are rounded up to one of these, for the same reason
+    pub quality_levels: Vec<u8>,
+    // Resized copies rendered at the same time, further requests wait for one to finish
+    pub max_concurrent_renders: usize,
+    pub cache_max_age_seconds: u32,
+    // How long an upload waits to be registered before it and its files are removed
+    pub pending_upload_ttl_seconds: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    // local (files under upload_dir) or s3
+    pub backend: String,
+    // Any S3 compatible service, e.g. https://s3.eu-central-1.amazonaws.com or http://localhost:9000 for MinIO
+    pub s3_endpoint: String,
+    pub s3_region: String,
+    pub s3_bucket: String,
+    // Put in front of every key, so the bucket can be shared
+    pub s3_prefix: String,
+    pub s3_access_key: String,
+    pub s3_secret_key: String,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    File(String, String),
+    Env(String, String),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::File(path, e) => write!(f, "could not read config file {}: {}", path, e),
+            ConfigError::Env(key, value) => write!(f, "invalid value {:?} for {}", value, key),
+            ConfigError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind_address: "0.0.0.0:1998".to_string(),
+            allowed_origins: vec!["http://localhost:5173".to_string()],
+            upload_dir: "images".to_string(),
+            password_cost: bcrypt::DEFAULT_COST,
+            trust_proxy: false,
+            trusted_proxy_hops: 1,
+            database: DatabaseConfig::default(),
+            session: SessionConfig::default(),
+            pagination: PaginationConfig::default(),
+            login: LoginConfig::default(),
+            mail: MailConfig::default(),
+            password_reset: PasswordResetConfig::default(),
+            two_factor: TwoFactorConfig::default(),
+            oidc: OidcConfig::default(),
+            images: ImagesConfig::default(),
+            storage: StorageConfig::default(),
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: String::new(),
+            max_connections: 10,
+        }
+    }
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            expiry_seconds: 1800,
+            secure: true,
+            http_only: true,
+            same_site: "strict".to_string(),
+        }
+    }
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        PaginationConfig {
+            default_limit: 10,
+            max_limit: 100,
+            public_default_limit: 12,
+            public_max_limit: 50,
+        }
+    }
+}
+
+impl Default for LoginConfig {
+    fn default() -> Self {
+        LoginConfig {
+            free_attempts: 3,
+            base_delay_seconds: 2,
+            max_attempts: 10,
+            lockout_seconds: 900,
+            window_seconds: 900,
+        }
+    }
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        MailConfig {
+            transport: "stdout".to_string(),
+            from: "Portfolio <no-reply@localhost>".to_string(),
+            dir: "mail".to_string(),
+            smtp_host: String::new(),
+            smtp_port: 587,
+            smtp_username: String::new(),
+            smtp_password: String::new(),
+            smtp_tls: "starttls".to_string(),
+        }
+    }
+}
+
+impl Default for PasswordResetConfig {
+    fn default() -> Self {
+        PasswordResetConfig {
+            url: "http://localhost:5173/reset-password".to_string(),
+            ttl_seconds: 3600,
+        }
+    }
+}
+
+impl Default for TwoFactorConfig {
+    fn default() -> Self {
+        TwoFactorConfig {
+            issuer: "Portfolio".to_string(),
+            require_for_admins: false,
+        }
+    }
+}
+
+impl Default for OidcConfig {
+    fn default() -> Self {
+        OidcConfig {
+            enabled: false,
+            issuer: String::new(),
+            client_id: String::new(),
+            client_secret: String::new(),
+            redirect_uri: "http://localhost:1998/auth/oidc/callback".to_string(),
+            scopes: "openid email profile".to_string(),
+            auto_provision: false,
+            default_role: 1,
+        }
+    }
+}
+
+impl Default for ImagesConfig {
+    fn default() -> Self {
+        ImagesConfig {
+            max_dimension: 2560,
+            variant_widths: vec![320, 768, 1280],
+            webp_quality: 80,
+            derivative_dir: "derivatives".to_string(),
+            derivative_step: 16,
+            quality_levels: vec![50, 65, 80, 95],
+            max_concurrent_renders: 2,
+            cache_max_age_seconds: 86400,
+            pending_upload_ttl_seconds: 3600,
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            backend: "local".to_string(),
+            s3_endpoint: String::new(),
+            s3_region: "us-east-1".to_string(),
+            s3_bucket: String::new(),
+            s3_prefix: String::new(),
+            s3_access_key: String::new(),
+            s3_secret_key: String::new(),
+        }
+    }
+}
+
+impl Config {
+    // Defaults, then the TOML file (CONFIG_FILE or ./config.toml if present), then env vars
+    pub fn load() -> Result<Config, ConfigError> {
+        let mut config = match std::env::var("CONFIG_FILE") {
+            Ok(path) => Config::from_file(&path)?,
+            Err(_) if Path::new("config.toml").exists() => Config::from_file("config.toml")?,
+            Err(_) => Config::default(),
+        };
+
+        config.apply_env()?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn from_file(path: &str) -> Result<Config, ConfigError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::File(path.to_string(), e.to_string()))?;
+
+        toml::from_str(&contents).map_err(|e| ConfigError::File(path.to_string(), e.to_string()))
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        env_override("BIND_ADDRESS", &mut self.bind_address)?;
+        env_override("UPLOAD_DIR", &mut self.upload_dir)?;
+        env_override("BCRYPT_COST", &mut self.password_cost)?;
+        env_override("TRUST_PROXY", &mut self.trust_proxy)?;
+        env_override("TRUSTED_PROXY_HOPS", &mut self.trusted_proxy_hops)?;
+        env_override("DATABASE_URL", &mut self.database.url)?;
+        env_override("DATABASE_MAX_CONNECTIONS", &mut self.database.max_connections)?;
+        env_override("SESSION_EXPIRY_SECONDS", &mut self.session.expiry_seconds)?;
+        env_override("SESSION_SECURE", &mut self.session.secure)?;
+        env_override("SESSION_HTTP_ONLY", &mut self.session.http_only)?;
+        env_override("SESSION_SAME_SITE", &mut self.session.same_site)?;
+        env_override("PAGINATION_DEFAULT_LIMIT", &mut self.pagination.default_limit)?;
+        env_override("PAGINATION_MAX_LIMIT", &mut self.pagination.max_limit)?;
+        env_override("PUBLIC_PAGINATION_DEFAULT_LIMIT", &mut self.pagination.public_default_limit)?;
+        env_override("PUBLIC_PAGINATION_MAX_LIMIT", &mut self.pagination.public_max_limit)?;
+        env_override("LOGIN_FREE_ATTEMPTS", &mut self.login.free_attempts)?;
+        env_override("LOGIN_BASE_DELAY_SECONDS", &mut self.login.base_delay_seconds)?;
+        env_override("LOGIN_MAX_ATTEMPTS", &mut self.login.max_attempts)?;
+        env_override("LOGIN_LOCKOUT_SECONDS", &mut self.login.lockout_seconds)?;
+        env_override("LOGIN_WINDOW_SECONDS", &mut self.login.window_seconds)?;
+        env_override("MAIL_TRANSPORT", &mut self.mail.transport)?;
+        env_override("MAIL_FROM", &mut self.mail.from)?;
+        env_override("MAIL_DIR", &mut self.mail.dir)?;
+        env_override("SMTP_HOST", &mut self.mail.smtp_host)?;
+        env_override("SMTP_PORT", &mut self.mail.smtp_port)?;
+        env_override("SMTP_USERNAME", &mut self.mail.smtp_username)?;
+        env_override("SMTP_PASSWORD", &mut self.mail.smtp_password)?;
+        env_override("SMTP_TLS", &mut self.mail.smtp_tls)?;
+        env_override("PASSWORD_RESET_URL", &mut self.password_reset.url)?;
+        env_override("PASSWORD_RESET_TTL_SECONDS", &mut self.password_reset.ttl_seconds)?;
+        env_override("TWO_FACTOR_ISSUER", &mut self.two_factor.issuer)?;
+        env_override("TWO_FACTOR_REQUIRE_FOR_ADMINS", &mut self.two_factor.require_for_admins)?;
+        env_override("OIDC_ENABLED", &mut self.oidc.enabled)?;
+        env_override("OIDC_ISSUER", &mut self.oidc.issuer)?;
+        env_override("OIDC_CLIENT_ID", &mut self.oidc.client_id)?;
+        env_override("OIDC_CLIENT_SECRET", &mut self.oidc.client_secret)?;
+        env_override("OIDC_REDIRECT_URI", &mut self.oidc.redirect_uri)?;
+        env_override("OIDC_SCOPES", &mut self.oidc.scopes)?;
+        env_override("OIDC_AUTO_PROVISION", &mut self.oidc.auto_provision)?;
+        env_override("OIDC_DEFAULT_ROLE", &mut self.oidc.default_role)?;
+        env_override("IMAGE_MAX_DIMENSION", &mut self.images.max_dimension)?;
+        env_override("IMAGE_WEBP_QUALITY", &mut self.images.webp_quality)?;
+        env_override("IMAGE_DERIVATIVE_DIR", &mut self.images.derivative_dir)?;
+        env_override("IMAGE_DERIVATIVE_STEP", &mut self.images.derivative_step)?;
+        env_override("IMAGE_MAX_CONCURRENT_RENDERS", &mut self.images.max_concurrent_renders)?;
+        env_override("IMAGE_CACHE_MAX_AGE_SECONDS", &mut self.images.cache_max_age_seconds)?;
+        env_override("IMAGE_PENDING_UPLOAD_TTL_SECONDS", &mut self.images.pending_upload_ttl_seconds)?;
+        env_override("STORAGE_BACKEND", &mut self.storage.backend)?;
+        env_override("S3_ENDPOINT", &mut self.storage.s3_endpoint)?;
+        env_override("S3_REGION", &mut self.storage.s3_region)?;
+        env_override("S3_BUCKET", &mut self.storage.s3_bucket)?;
+        env_override("S3_PREFIX", &mut self.storage.s3_prefix)?;
+        env_override("S3_ACCESS_KEY", &mut self.storage.s3_access_key)?;
+        env_override("S3_SECRET_KEY", &mut self.storage.s3_secret_key)?;
+
+        if let Ok(origins) = std::env::var("ALLOWED_ORIGINS") {
+            self.allowed_origins = origins
+                .split(',')
+                .map(|origin| origin.trim().to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect();
+        }
+
+        if let Ok(widths) = std::env::var("IMAGE_VARIANT_WIDTHS") {
+            self.images.variant_widths = widths
+                .split(',')
+                .map(|width| width.trim())
+                .filter(|width| !width.is_empty())
+                .map(|width| width.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| ConfigError::Env("IMAGE_VARIANT_WIDTHS".to_string(), widths.clone()))?;
+        }
+
+        if let Ok(levels) = std::env::var("IMAGE_QUALITY_LEVELS") {
+            self.images.quality_levels = levels
+                .split(',')
+                .map(|level| level.trim())
+                .filter(|level| !level.is_empty())
+                .map(|level| level.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| ConfigError::Env("IMAGE_QUALITY_LEVELS".to_string(), levels.clone()))?;
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.database.url.is_empty() {
+            return Err(ConfigError::Invalid(
+                "DATABASE_URL must be set (or database.url in the config file)".to_string(),
+            ));
+        }
+        if self.database.max_connections == 0 {
+            return Err(ConfigError::Invalid(
+                "database.max_connections must be greater than 0".to_string(),
+            ));
+        }
+        if self.bind_address.parse::<SocketAddr>().is_err() {
+            return Err(ConfigError::Invalid(format!(
+                "bind_address {:?} is not a valid socket address",
+                self.bind_address
+            )));
+        }
+        if self.allowed_origins.is_empty() {
+            return Err(ConfigError::Invalid(
+                "allowed_origins must contain at least one origin".to_string(),
+            ));
+        }
+        for origin in &self.allowed_origins {
+            let is_http = origin.starts_with("http://") || origin.starts_with("https://");
+            if !is_http || origin.ends_with('/') || origin.parse::<HeaderValue>().is_err() {
+                return Err(ConfigError::Invalid(format!(
+                    "allowed origin {:?} must look like scheme://host[:port]",
+                    origin
+                )));
+            }
+        }
+        if self.upload_dir.trim().is_empty() {
+            return Err(ConfigError::Invalid("upload_dir must not be empty".to_string()));
+        }
+        if self.trusted_proxy_hops == 0 {
+            return Err(ConfigError::Invalid(
+                "trusted_proxy_hops must be greater than 0".to_string(),
+            ));
+        }
+        if !(4..=31).contains(&self.password_cost) {
+            return Err(ConfigError::Invalid(
+                "password_cost must be between 4 and 31".to_string(),
+            ));
+        }
+        if self.session.expiry_seconds <= 0 {
+            return Err(ConfigError::Invalid(
+                "session.expiry_seconds must be greater than 0".to_string(),
+            ));
+        }
+        self.session.same_site()?;
+
+        let pagination = &self.pagination;
+        if pagination.default_limit == 0 || pagination.default_limit > pagination.max_limit {
+            return Err(ConfigError::Invalid(
+                "pagination.default_limit must be between 1 and pagination.max_limit".to_string(),
+            ));
+        }
+        if pagination.public_default_limit == 0
+            || pagination.public_default_limit > pagination.public_max_limit
+        {
+            return Err(ConfigError::Invalid(
+                "pagination.public_default_limit must be between 1 and pagination.public_max_limit"
+                    .to_string(),
+            ));
+        }
+
+        let login = &self.login;
+        if login.free_attempts < 0 || login.max_attempts <= login.free_attempts {
+            return Err(ConfigError::Invalid(
+                "login.max_attempts must be greater than login.free_attempts".to_string(),
+            ));
+        }
+        if login.base_delay_seconds <= 0 || login.lockout_seconds <= 0 || login.window_seconds <= 0 {
+            return Err(ConfigError::Invalid(
+                "login.base_delay_seconds, login.lockout_seconds and login.window_seconds must be greater than 0"
+                    .to_string(),
+            ));
+        }
+
+        let mail = &self.mail;
+        if mail.from.parse::<lettre::message::Mailbox>().is_err() {
+            return Err(ConfigError::Invalid(format!(
+                "mail.from {:?} is not a valid address",
+                mail.from
+            )));
+        }
+        match mail.transport.as_str() {
+            "stdout" => {}
+            "file" if !mail.dir.trim().is_empty() => {}
+            "file" => return Err(ConfigError::Invalid("mail.dir must not be empty".to_string())),
+            "smtp" if mail.smtp_host.is_empty() => {
+                return Err(ConfigError::Invalid(
+                    "mail.smtp_host must be set for the smtp transport".to_string(),
+                ))
+            }
+            "smtp" => {
+                if !["starttls", "tls", "none"].contains(&mail.smtp_tls.as_str()) {
+                    return Err(ConfigError::Invalid(format!(
+                        "mail.smtp_tls {:?} must be one of starttls, tls, none",
+                        mail.smtp_tls
+                    )));
+                }
+            }
+            _ => {
+                return Err(ConfigError::Invalid(format!(
+                    "mail.transport {:?} must be one of stdout, file, smtp",
+                    mail.transport
+                )))
+            }
+        }
+        if self.password_reset.ttl_seconds <= 0 {
+            return Err(ConfigError::Invalid(
+                "password_reset.ttl_seconds must be greater than 0".to_string(),
+            ));
+        }
+        if self.two_factor.issuer.trim().is_empty() || self.two_factor.issuer.contains(':') {
+            return Err(ConfigError::Invalid(
+                "two_factor.issuer must not be empty or contain ':'".to_string(),
+            ));
+        }
+        if self.oidc.enabled {
+            self.oidc.validate()?;
+        }
+
+        let images = &self.images;
+        if !(1..=16384).contains(&images.max_dimension) {
+            return Err(ConfigError::Invalid(
+                "images.max_dimension must be between 1 and 16384".to_string(),
+            ));
+        }
+        if images
+            .variant_widths
+            .iter()
+            .any(|width| *width == 0 || *width >= images.max_dimension)
+        {
+            return Err(ConfigError::Invalid(
+                "images.variant_widths must be greater than 0 and smaller than images.max_dimension".to_string(),
+            ));
+        }
+        if !(1..=100).contains(&images.webp_quality) {
+            return Err(ConfigError::Invalid(
+                "images.webp_quality must be between 1 and 100".to_string(),
+            ));
+        }
+        if images.derivative_dir.trim().is_empty() || images.derivative_dir == self.upload_dir {
+            return Err(ConfigError::Invalid(
+                "images.derivative_dir must be set and differ from upload_dir".to_string(),
+            ));
+        }
+        if images.derivative_step == 0 {
+            return Err(ConfigError::Invalid(
+                "images.derivative_step must be greater than 0".to_string(),
+            ));
+        }
+        if images.quality_levels.is_empty() || images.quality_levels.iter().any(|q| !(1..=100).contains(q)) {
+            return Err(ConfigError::Invalid(
+                "images.quality_levels must list qualities between 1 and 100".to_string(),
+            ));
+        }
+        if images.max_concurrent_renders == 0 {
+            return Err(ConfigError::Invalid(
+                "images.max_concurrent_renders must be greater than 0".to_string(),
+            ));
+        }
+        if images.pending_upload_ttl_seconds <= 0 {
+            return Err(ConfigError::Invalid(
+                "images.pending_upload_ttl_seconds must be greater than 0".to_string(),
+            ));
+        }
+
+        match self.storage.backend.as_str() {
+            "local" => {}
+            "s3" => self.storage.validate()?,
+            _ => {
+                return Err(ConfigError::Invalid(format!(
+                    "storage.backend {:?} must be one of local, s3",
+                    self.storage.backend
+                )))
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn origins(&self) -> Vec<HeaderValue> {
+        self.allowed_origins
+            .iter()
+            .filter_map(|origin| origin.parse::<HeaderValue>().ok())
+            .collect()
+    }
+}
+
+impl SessionConfig {
+    pub fn same_site(&self) -> Result<SameSite, ConfigError> {
+        match self.same_site.to_ascii_lowercase().as_str() {
+            "strict" => Ok(SameSite::Strict),
+            "lax" => Ok(SameSite::Lax),
+            "none" => Ok(SameSite::None),
+            _ => Err(ConfigError::Invalid(format!(
+                "session.same_site {:?} must be one of strict, lax, none",
+                self.same_site
+            ))),
+        }
+    }
+}
+
+impl OidcConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.client_id.is_empty() {
+            return Err(ConfigError::Invalid("oidc.client_id must be set".to_string()));
+        }
+        for (key, value) in [("oidc.issuer", &self.issuer), ("oidc.redirect_uri", &self.redirect_uri)] {
+            if !is_secure_url(value) {
+                return Err(ConfigError::Invalid(format!(
+                    "{} {:?} must be an https URL (http is only allowed for localhost)",
+                    key, value
+                )));
+            }
+        }
+        let scopes: Vec<&str> = self.scopes.split_whitespace().collect();
+        if !scopes.contains(&"openid") || !scopes.contains(&"email") {
+            return Err(ConfigError::Invalid(
+                "oidc.scopes must include openid and email".to_string(),
+            ));
+        }
+        Role::try_from(self.default_role)
+            .map_err(|e| ConfigError::Invalid(format!("oidc.default_role: {}", e)))?;
+        Ok(())
+    }
+}
+
+impl StorageConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        let endpoint_ok = reqwest::Url::parse(&self.s3_endpoint)
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host_str().is_some());
+        if !endpoint_ok {
+            return Err(ConfigError::Invalid(format!(
+                "storage.s3_endpoint {:?} must be an http or https URL",
+                self.s3_endpoint
+            )));
+        }
+        for (key, value) in [
+            ("storage.s3_region", &self.s3_region),
+            ("storage.s3_bucket", &self.s3_bucket),
+            ("storage.s3_access_key", &self.s3_access_key),
+            ("storage.s3_secret_key", &self.s3_secret_key),
+        ] {
+            if value.trim().is_empty() {
+                return Err(ConfigError::Invalid(format!("{} must be set for the s3 backend", key)));
+            }
+        }
+        let prefix = self.s3_prefix.trim_matches('/');
+        if !prefix.is_empty() && validate_key(prefix).is_err() {
+            return Err(ConfigError::Invalid(format!(
+                "storage.s3_prefix {:?} is not a valid key prefix",
+                self.s3_prefix
+            )));
+        }
+        Ok(())
+    }
+}
+
+// Plain http is fine for a mock provider on this machine, anything else has to use TLS
+fn is_secure_url(value: &str) -> bool {
+    match reqwest::Url::parse(value) {
+        Ok(url) => match url.scheme() {
+            "https" => true,
+            "http" => matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")),
+            _ => false,
+        },
+        Err(_) => false,
+    }
+}
+
+impl LoginConfig {
+    // Seconds a subject has to wait after its nth consecutive failure
+    pub fn delay(&self, failures: i32) -> i64 {
+        if failures >= self.max_attempts {
+            return self.lockout_seconds;
+        }
+        if failures < self.free_attempts {
+            return 0;
+        }
+
+        let exponent = (failures - self.free_attempts).min(30) as u32;
+        self.base_delay_seconds
+            .saturating_mul(1 << exponent)
+            .min(self.lockout_seconds)
+    }
+}
+
+impl PaginationConfig {
+    // Returns (limit, offset) clamped to the configured bounds
+    pub fn page(&self, page: Option<usize>, limit: Option<usize>) -> (i64, i64) {
+        paginate(page, limit, self.default_limit, self.max_limit)
+    }
+
+    pub fn public_page(&self, page: Option<usize>, limit: Option<usize>) -> (i64, i64) {
+        paginate(page, limit, self.public_default_limit, self.public_max_limit)
+    }
+}
+
+fn paginate(
+    page: Option<usize>,
+    limit: Option<usize>,
+    default_limit: usize,
+    max_limit: usize,
+) -> (i64, i64) {
+    let limit = limit.unwrap_or(default_limit).clamp(1, max_limit);
+    let page = page.unwrap_or(1).max(1);
+    let offset = (page - 1).saturating_mul(limit).min(i32::MAX as usize);
+
+    (limit as i64, offset as i64)
+}
+
+fn env_override<T: FromStr>(key: &str, target: &mut T) -> Result<(), ConfigError> {
+    if let Ok(value) = std::env::var(key) {
+        *target = value
+            .parse::<T>()
+            .map_err(|_| ConfigError::Env(key.to_string(), value))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use crate::{
+    error::ApiError,
+    resource::schema::{Fields, Filter, Resource},
+    detail::{
+        model::DetailModel,
+        schema::{CreateDetailSchema, UpdateDetailSchema},
+    },
+    AppState,
+};
+
+pub struct DetailResource;
+
+impl Resource for DetailResource {
+    type Model = DetailModel;
+    type Create = CreateDetailSchema;
+    type Update = UpdateDetailSchema;
+
+    const TABLE: &'static str = "details";
+    const PATH: &'static str = "/details";
+    const SORTABLE: &'static [&'static str] = &["created_at", "updated_at", "title"];
+    const FILTERABLE: &'static [(&'static str, Filter)] = &[("title", Filter::Contains), ("company", Filter::Contains)];
+
+    async fn create_fields(body: CreateDetailSchema, _: &AppState) -> Result<Fields, ApiError> {
+        Ok(Fields::new()
+            .set("title", body.title)
+            .set("logo", body.logo)
+            .set("keywords", body.keywords)
+            .set("site_description", body.site_description)
+            .set("description", body.description)
+            .set("about", body.about)
+            .set("position", body.position)
+            .set("company", body.company)
+            .set("img", body.img))
+    }
+
+    async fn update_fields(body: UpdateDetailSchema, _: &AppState) -> Result<Fields, ApiError> {
+        Ok(Fields::new()
+            .set_some("title", body.title)
+            .set_some("logo", body.logo)
+            .set_some("keywords", body.keywords)
+            .set_some("site_description", body.site_description)
+            .set_some("description", body.description)
+            .set_some("about", body.about)
+            .set_some("position", body.position)
+            .set_some("company", body.company)
+            .set_some("img", body.img))
+    }
+}
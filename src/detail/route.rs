@@ -1,26 +1,9 @@
 use std::sync::Arc;
 
-use axum::{
-    routing::{get, post},
-    Router,
-};
+use axum::Router;
 
-use crate::{
-    detail::handler::{
-        create_detail_handler, delete_detail_handler, detail_list_handler, edit_detail_handler,
-        get_detail_handler,
-    },
-    AppState,
-};
+use crate::{resource::route::resource_router, detail::resource::DetailResource, AppState};
 
 pub fn detail_router(app_state: Arc<AppState>) -> Router {
-    Router::new()
-        .route("/details", get(detail_list_handler))
-        .route("/details", post(create_detail_handler))
-        .route(
-            "/details/:id",
-            get(get_detail_handler)
-                .patch(edit_detail_handler)
-                .delete(delete_detail_handler),
-        ).with_state(app_state)
+    resource_router::<DetailResource>(app_state)
 }
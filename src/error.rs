@@ -0,0 +1,187 @@
+use std::fmt;
+
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::{header::RETRY_AFTER, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::storage::store::StorageError;
+
+// SQLSTATE codes, see https://www.postgresql.org/docs/current/errcodes-appendix.html
+const UNIQUE_VIOLATION: &str = "23505";
+const FOREIGN_KEY_VIOLATION: &str = "23503";
+const NOT_NULL_VIOLATION: &str = "23502";
+const CHECK_VIOLATION: &str = "23514";
+const STRING_DATA_RIGHT_TRUNCATION: &str = "22001";
+const INVALID_TEXT_REPRESENTATION: &str = "22P02";
+
+#[derive(Debug)]
+pub enum ApiError {
+    NotFound(String),
+    Conflict(String),
+    // A request that couldn't be read at all, malformed JSON or a bad path or query parameter
+    BadRequest(String),
+    Validation(String),
+    Unauthorized(String),
+    Forbidden(String),
+    // Seconds until the client may retry
+    TooManyRequests(i64),
+    // The message is logged but never sent to the client
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn not_found(id: impl fmt::Display) -> Self {
+        ApiError::NotFound(format!("Item with ID: {} not found", id))
+    }
+
+    pub fn internal(e: impl fmt::Display) -> Self {
+        ApiError::Internal(e.to_string())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::TooManyRequests(_) => "too_many_requests",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            ApiError::NotFound(message)
+            | ApiError::Conflict(message)
+            | ApiError::BadRequest(message)
+            | ApiError::Validation(message)
+            | ApiError::Unauthorized(message)
+            | ApiError::Forbidden(message) => message.to_owned(),
+            ApiError::TooManyRequests(seconds) => {
+                format!("Too many attempts, try again in {} seconds", seconds)
+            }
+            ApiError::Internal(_) => "Something went wrong".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Internal(message) => write!(f, "{}: {}", self.code(), message),
+            _ => write!(f, "{}: {}", self.code(), self.message()),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if let ApiError::Internal(message) = &self {
+            eprintln!("Internal error: {}", message);
+        }
+
+        let status = if self.status().is_server_error() {
+            "error"
+        } else {
+            "fail"
+        };
+
+        let error_response = serde_json::json!({
+            "status": status,
+            "code": self.code(),
+            "message": self.message(),
+        });
+
+        let mut response = (self.status(), Json(error_response)).into_response();
+        if let ApiError::TooManyRequests(seconds) = self {
+            response.headers_mut().insert(RETRY_AFTER, seconds.max(1).into());
+        }
+        response
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        match &e {
+            sqlx::Error::RowNotFound => ApiError::NotFound("Item not found".to_string()),
+            sqlx::Error::Database(db_error) => match db_error.code().as_deref() {
+                Some(UNIQUE_VIOLATION) => {
+                    ApiError::Conflict("An item with the same unique value already exists".to_string())
+                }
+                Some(FOREIGN_KEY_VIOLATION) => {
+                    ApiError::Conflict("Item is referenced by or references a missing item".to_string())
+                }
+                Some(NOT_NULL_VIOLATION)
+                | Some(CHECK_VIOLATION)
+                | Some(STRING_DATA_RIGHT_TRUNCATION)
+                | Some(INVALID_TEXT_REPRESENTATION) => ApiError::Validation(db_error.message().to_string()),
+                _ => ApiError::internal(e),
+            },
+            _ => ApiError::internal(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for ApiError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => ApiError::NotFound("File not found".to_string()),
+            _ => ApiError::internal(e),
+        }
+    }
+}
+
+impl From<StorageError> for ApiError {
+    fn from(e: StorageError) -> Self {
+        match e {
+            StorageError::NotFound => ApiError::NotFound("File not found".to_string()),
+            StorageError::InvalidKey(key) => ApiError::Validation(format!("{:?} is not a valid file name", key)),
+            StorageError::Backend(_) => ApiError::internal(e),
+        }
+    }
+}
+
+// Well-formed bodies with the wrong fields stay validation errors, as axum has them
+fn rejected(status: StatusCode, message: String) -> ApiError {
+    match status {
+        StatusCode::UNPROCESSABLE_ENTITY => ApiError::Validation(message),
+        status if status.is_server_error() => ApiError::Internal(message),
+        _ => ApiError::BadRequest(message),
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        rejected(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        rejected(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        rejected(rejection.status(), rejection.body_text())
+    }
+}
@@ -1,11 +1,13 @@
 use std::sync::Arc;
 
 use axum::{
+    middleware,
     routing::get,
     Router,
 };
 
 use crate::{
+    auth::{role::Access, route::require_permission},
     general::handler::update_translation_file,
     AppState,
 };
@@ -16,5 +18,6 @@ pub fn general_router(app_state: Arc<AppState>) -> Router {
             "/update/translation_files",
             get(update_translation_file),
         )
+        .route_layer(middleware::from_fn_with_state(Access::PUBLISH, require_permission))
         .with_state(app_state)
 }
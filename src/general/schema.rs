@@ -5,10 +5,6 @@ pub struct FilterOptions {
     pub page: Option<usize>,
     pub limit: Option<usize>,
 }
-#[derive(Deserialize, Debug)]
-pub struct ParamOptions {
-    pub id: String,
-}
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Table {
     pub count: Option<i64>,
@@ -0,0 +1,302 @@
+use std::io::Cursor;
+
+use image::{
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder, png::PngEncoder},
+    imageops::FilterType,
+    DynamicImage, GenericImageView, ImageDecoder, ImageFormat, ImageReader,
+};
+
+use crate::config::ImagesConfig;
+use crate::error::ApiError;
+use crate::image::process::encode_webp;
+use crate::image::schema::ImageQuery;
+
+const DEFAULT_QUALITY: u8 = 80;
+// rav1e speed from 1 (slowest) to 10, derivatives are cached so only the first request pays for it
+const AVIF_SPEED: u8 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fit {
+    // Fits inside the box, keeping the aspect ratio
+    Contain,
+    // Fills the box, cropping what sticks out
+    Cover,
+    // Stretches to the box
+    Fill,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Avif,
+    WebP,
+    Jpeg,
+    Png,
+}
+
+impl Fit {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Fit::Contain => "contain",
+            Fit::Cover => "cover",
+            Fit::Fill => "fill",
+        }
+    }
+}
+
+impl OutputFormat {
+    // By extension, the way the stored name says it
+    pub fn from_name(name: &str) -> Option<Self> {
+        match ImageFormat::from_path(name).ok()? {
+            ImageFormat::Avif => Some(OutputFormat::Avif),
+            ImageFormat::WebP => Some(OutputFormat::WebP),
+            ImageFormat::Jpeg => Some(OutputFormat::Jpeg),
+            ImageFormat::Png => Some(OutputFormat::Png),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Avif => "image/avif",
+            OutputFormat::WebP => "image/webp",
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Png => "image/png",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Avif => "avif",
+            OutputFormat::WebP => "webp",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Png => "png",
+        }
+    }
+
+    // PNG is the only output q doesn't apply to
+    fn is_lossy(&self) -> bool {
+        matches!(self, OutputFormat::Avif | OutputFormat::WebP | OutputFormat::Jpeg)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DerivativeOptions {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fit: Fit,
+    pub quality: u8,
+}
+
+impl DerivativeOptions {
+    pub fn from_query(query: &ImageQuery, config: &ImagesConfig) -> Result<Self, ApiError> {
+        let width = parse_dimension("w", query.w.as_deref(), config)?;
+        let height = parse_dimension("h", query.h.as_deref(), config)?;
+
+        let fit = match query.fit.as_deref().unwrap_or_default() {
+            "" | "contain" => Fit::Contain,
+            "cover" => Fit::Cover,
+            "fill" => Fit::Fill,
+            fit => {
+                return Err(ApiError::Validation(format!(
+                    "fit {:?} must be one of contain, cover, fill",
+                    fit
+                )))
+            }
+        };
+        // The fit only changes anything when both sides are given
+        let fit = if width.is_some() && height.is_some() {
+            fit
+        } else {
+            Fit::Contain
+        };
+
+        let quality = match query.q.as_deref().unwrap_or_default() {
+            "" => DEFAULT_QUALITY,
+            q => q
+                .parse::<u8>()
+                .ok()
+                .filter(|q| (1..=100).contains(q))
+                .ok_or_else(|| ApiError::Validation("q must be between 1 and 100".to_string()))?,
+        };
+        let quality = quality_level(quality, &config.quality_levels);
+
+        Ok(DerivativeOptions {
+            width,
+            height,
+            fit,
+            quality,
+        })
+    }
+
+    pub fn resizes(&self) -> bool {
+        self.width.is_some() || self.height.is_some()
+    }
+
+    // File name of the cached copy, different for every distinct output
+    pub fn cache_name(&self, format: OutputFormat) -> String {
+        let quality = if format.is_lossy() {
+            format!("-q{}", self.quality)
+        } else {
+            String::new()
+        };
+
+        format!(
+            "{}x{}-{}{}.{}",
+            self.width.unwrap_or(0),
+            self.height.unwrap_or(0),
+            self.fit.as_str(),
+            quality,
+            format.extension()
+        )
+    }
+}
+
+// Sizes are capped and rounded up to the configured step, so one image can't be made to fill the
+// cache with every width from 1 to max_dimension
+fn parse_dimension(name: &str, value: Option<&str>, config: &ImagesConfig) -> Result<Option<u32>, ApiError> {
+    let value = match value {
+        None | Some("") => return Ok(None),
+        Some(value) => value,
+    };
+
+    let size = value
+        .parse::<u32>()
+        .ok()
+        .filter(|size| (1..=config.max_dimension).contains(size))
+        .ok_or_else(|| {
+            ApiError::Validation(format!("{} must be between 1 and {}", name, config.max_dimension))
+        })?;
+
+    let step = config.derivative_step;
+    Ok(Some((size.div_ceil(step) * step).min(config.max_dimension)))
+}
+
+// The lowest configured level at or above q, or the highest there is
+fn quality_level(quality: u8, levels: &[u8]) -> u8 {
+    levels
+        .iter()
+        .copied()
+        .filter(|level| *level >= quality)
+        .min()
+        .or_else(|| levels.iter().copied().max())
+        .unwrap_or(quality)
+}
+
+// How specifically Accept names `mime` (2 exact, 1 image/*, 0 */*) and with what q-value
+fn accepted(accept: &str, mime: &str) -> Option<(u8, f32)> {
+    let mut best: Option<(u8, f32)> = None;
+
+    for entry in accept.split(',') {
+        let mut parts = entry.split(';').map(str::trim);
+        let media = parts.next().unwrap_or_default().to_ascii_lowercase();
+
+        let specificity = match media.as_str() {
+            media if media == mime => 2,
+            "image/*" => 1,
+            "*/*" => 0,
+            _ => continue,
+        };
+        let q = parts
+            .find_map(|param| param.strip_prefix("q="))
+            .and_then(|q| q.parse().ok())
+            .unwrap_or(1.0);
+
+        if best.is_none_or(|(current, _)| specificity > current) {
+            best = Some((specificity, q));
+        }
+    }
+
+    best
+}
+
+// AVIF only when the client names it, `*/*` alone is no promise it can decode it. Sources with
+// transparency fall back to PNG rather than JPEG
+pub fn negotiate(accept: Option<&str>, has_alpha: impl FnOnce() -> bool) -> OutputFormat {
+    let accept = accept.unwrap_or("*/*");
+
+    if matches!(accepted(accept, "image/avif"), Some((2, q)) if q > 0.0) {
+        return OutputFormat::Avif;
+    }
+    if matches!(accepted(accept, "image/webp"), Some((_, q)) if q > 0.0) {
+        return OutputFormat::WebP;
+    }
+    if !has_alpha() && matches!(accepted(accept, "image/jpeg"), Some((_, q)) if q > 0.0) {
+        return OutputFormat::Jpeg;
+    }
+    OutputFormat::Png
+}
+
+// Reads only the header
+pub fn has_alpha(source: &[u8]) -> Result<bool, ApiError> {
+    let decoder = ImageReader::new(Cursor::new(source))
+        .with_guessed_format()?
+        .into_decoder()
+        .map_err(ApiError::internal)?;
+
+    Ok(decoder.color_type().has_alpha())
+}
+
+// CPU bound, callers run it with spawn_blocking
+pub fn render(source: &[u8], options: &DerivativeOptions, format: OutputFormat) -> Result<Vec<u8>, ApiError> {
+    let image = ImageReader::new(Cursor::new(source))
+        .with_guessed_format()?
+        .decode()
+        .map_err(ApiError::internal)?;
+
+    encode(&resize(image, options), format, options.quality)
+}
+
+// Never scales up, a box larger than the image is shrunk to fit it first
+fn resize(image: DynamicImage, options: &DerivativeOptions) -> DynamicImage {
+    let (source_width, source_height) = image.dimensions();
+
+    match (options.width, options.height, options.fit) {
+        (None, None, _) => image,
+        (Some(width), Some(height), Fit::Cover) => {
+            let scale = (source_width as f64 / width as f64)
+                .min(source_height as f64 / height as f64)
+                .min(1.0);
+            let width = ((width as f64 * scale).round() as u32).max(1);
+            let height = ((height as f64 * scale).round() as u32).max(1);
+
+            image.resize_to_fill(width, height, FilterType::Lanczos3)
+        }
+        (Some(width), Some(height), Fit::Fill) => image.resize_exact(
+            width.min(source_width),
+            height.min(source_height),
+            FilterType::Lanczos3,
+        ),
+        (width, height, _) => {
+            let width = width.unwrap_or(u32::MAX).min(source_width);
+            let height = height.unwrap_or(u32::MAX).min(source_height);
+
+            if (width, height) == (source_width, source_height) {
+                image
+            } else {
+                image.resize(width, height, FilterType::Lanczos3)
+            }
+        }
+    }
+}
+
+fn encode(image: &DynamicImage, format: OutputFormat, quality: u8) -> Result<Vec<u8>, ApiError> {
+    if format == OutputFormat::WebP {
+        return encode_webp(image, quality);
+    }
+
+    let mut bytes = Vec::new();
+    let result = match format {
+        OutputFormat::Jpeg => {
+            DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, quality))
+        }
+        OutputFormat::Avif => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_with_encoder(AvifEncoder::new_with_speed_quality(&mut bytes, AVIF_SPEED, quality)),
+        _ if image.color().has_alpha() => {
+            DynamicImage::ImageRgba8(image.to_rgba8()).write_with_encoder(PngEncoder::new(&mut bytes))
+        }
+        _ => DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(PngEncoder::new(&mut bytes)),
+    };
+    result.map_err(ApiError::internal)?;
+
+    Ok(bytes)
+}
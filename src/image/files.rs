@@ -0,0 +1,182 @@
+use sqlx::{Postgres, Transaction};
+
+use crate::error::ApiError;
+use crate::image::process::variant_name;
+use crate::storage::store::{Storage, StorageError};
+use crate::AppState;
+
+// Uploads are stored under generated names below this prefix, /images/:name never reaches them directly
+pub const UPLOAD_PREFIX: &str = "uploads";
+
+// Named after the SHA-256 of the stored bytes, so identical uploads share one file
+pub fn content_key(sha256: &str) -> String {
+    format!("{}/{}.webp", UPLOAD_PREFIX, sha256)
+}
+
+pub async fn exists(storage: &dyn Storage, key: &str) -> Result<bool, ApiError> {
+    match storage.stat(key).await {
+        Ok(_) => Ok(true),
+        Err(StorageError::NotFound) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn existing_variants(storage: &dyn Storage, key: &str, widths: &[i32]) -> Result<Vec<i32>, ApiError> {
+    let mut existing = Vec::new();
+    for width in widths {
+        if exists(storage, &variant_name(key, *width)).await? {
+            existing.push(*width);
+        }
+    }
+    existing.sort_unstable();
+    existing.dedup();
+    Ok(existing)
+}
+
+// Deletes a stored file with its variants and resized copies, skipping whatever is already gone
+pub async fn remove_files(data: &AppState, key: &str, variants: &[i32]) {
+    let _ = data.storage.delete(key).await;
+    for width in variants {
+        let _ = data.storage.delete(&variant_name(key, *width)).await;
+    }
+    remove_derivatives(data, key).await;
+}
+
+pub async fn remove_derivatives(data: &AppState, key: &str) {
+    if let Ok(derivatives) = data.derivatives.list(&format!("{}/", key)).await {
+        for derivative in derivatives {
+            let _ = data.derivatives.delete(&derivative).await;
+        }
+    }
+}
+
+// Moves a stored file with its variants, putting back what already moved when one of them fails
+pub async fn move_files(data: &AppState, from: &str, to: &str, variants: &[i32]) -> Result<(), ApiError> {
+    let mut moves = vec![(from.to_string(), to.to_string())];
+    moves.extend(variants.iter().map(|width| (variant_name(from, *width), variant_name(to, *width))));
+
+    for (done, (source, target)) in moves.iter().enumerate() {
+        if let Err(e) = data.storage.rename(source, target).await {
+            for (source, target) in moves[..done].iter().rev() {
+                let _ = data.storage.rename(target, source).await;
+            }
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
+// Serialises everything that counts, releases or removes the file under key, until the transaction ends
+pub async fn lock_blob(tx: &mut Transaction<'_, Postgres>, key: &str) -> Result<(), ApiError> {
+    sqlx::query!("SELECT pg_advisory_xact_lock(hashtext($1))", key)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+// Counts one more image or pending upload pointing at the file, starting the count for a new one.
+// Returns whether it is the first
+pub async fn acquire_blob(tx: &mut Transaction<'_, Postgres>, key: &str, variants: &[i32]) -> Result<bool, ApiError> {
+    lock_blob(tx, key).await?;
+    let ref_count = sqlx::query_scalar!(
+        "INSERT INTO image_blobs (storage_key,variants,ref_count) VALUES ($1, $2, 1)
+        ON CONFLICT (storage_key) DO UPDATE SET ref_count = image_blobs.ref_count + 1,
+            variants = ARRAY(SELECT DISTINCT unnest(image_blobs.variants || EXCLUDED.variants) ORDER BY 1)
+        RETURNING ref_count",
+        key,
+        variants
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    Ok(ref_count == 1)
+}
+
+// Counts one reference less. When it was the last, the row goes and the variants of the file are
+// returned, for remove_released once the transaction has committed
+pub async fn release_blob(tx: &mut Transaction<'_, Postgres>, key: &str) -> Result<Option<Vec<i32>>, ApiError> {
+    lock_blob(tx, key).await?;
+    let blob = sqlx::query!(
+        "UPDATE image_blobs SET ref_count = ref_count - 1 WHERE storage_key = $1 RETURNING ref_count, variants",
+        key
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    match blob {
+        Some(blob) if blob.ref_count == 0 => {
+            sqlx::query!("DELETE FROM image_blobs WHERE storage_key = $1", key)
+                .execute(&mut **tx)
+                .await?;
+            Ok(Some(blob.variants))
+        }
+        _ => Ok(None),
+    }
+}
+
+// Moves the count of a file to the key it was renamed to
+pub async fn rekey_blob(tx: &mut Transaction<'_, Postgres>, from: &str, to: &str) -> Result<(), ApiError> {
+    lock_blob(tx, from).await?;
+    lock_blob(tx, to).await?;
+    sqlx::query!("UPDATE image_blobs SET storage_key = $1 WHERE storage_key = $2", to, from)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+// Deletes the files of a released blob, unless something counted it again since. The check and the
+// deletion hold the blob's lock, so an acquire_blob for the same key waits for them to finish, and
+// one that got in first is seen
+pub async fn remove_released(data: &AppState, key: &str, variants: &[i32]) -> Result<(), ApiError> {
+    let mut tx = data.db.begin().await?;
+    lock_blob(&mut tx, key).await?;
+
+    let counted = sqlx::query_scalar!("SELECT EXISTS (SELECT 1 FROM image_blobs WHERE storage_key = $1)", key)
+        .fetch_one(&mut *tx)
+        .await?
+        .unwrap_or(true);
+
+    if !counted {
+        remove_files(data, key, variants).await;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn last_release_returns_the_variants(db: PgPool) {
+        let mut tx = db.begin().await.unwrap();
+        assert!(acquire_blob(&mut tx, "uploads/a.webp", &[640]).await.unwrap());
+        assert!(!acquire_blob(&mut tx, "uploads/a.webp", &[320, 640]).await.unwrap());
+
+        assert_eq!(release_blob(&mut tx, "uploads/a.webp").await.unwrap(), None);
+        assert_eq!(release_blob(&mut tx, "uploads/a.webp").await.unwrap(), Some(vec![320, 640]));
+        assert_eq!(release_blob(&mut tx, "uploads/a.webp").await.unwrap(), None);
+        tx.commit().await.unwrap();
+    }
+
+    #[sqlx::test]
+    async fn acquire_waits_for_the_blob_lock(db: PgPool) {
+        // Held the way remove_released holds it, with no row left to lock
+        let mut removing = db.begin().await.unwrap();
+        lock_blob(&mut removing, "uploads/a.webp").await.unwrap();
+
+        let acquiring = tokio::spawn(async move {
+            let mut tx = db.begin().await.unwrap();
+            acquire_blob(&mut tx, "uploads/a.webp", &[]).await.unwrap();
+            tx.commit().await.unwrap();
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!acquiring.is_finished());
+
+        removing.commit().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), acquiring).await.unwrap().unwrap();
+    }
+}
@@ -1,237 +1,567 @@
 use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::{Postgres, Transaction};
 use std::sync::Arc;
-use tokio_util::codec::{BytesCodec, FramedRead};
+use tokio::sync::OnceCell;
+use uuid::Uuid;
 
 use axum::extract::Multipart;
-use futures_util::stream::StreamExt;
-use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
 
 use axum::{
-    extract::{Path, Query, State},
-    http::{Response, StatusCode},
+    body::Body,
+    extract::State,
+    http::{
+        header::{ACCEPT, CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH, LAST_MODIFIED, VARY},
+        HeaderMap, StatusCode,
+    },
     response::IntoResponse,
-    Json,
+    Extension,
 };
 
+use crate::extract::{Json, Path, Query};
+use crate::audit::log::{self, Action, Entry};
+use crate::auth::role::Principal;
+use crate::error::ApiError;
 use crate::general::schema::{FilterOptions, Table};
-use crate::image::{model::ImageModel, schema::UpdateImageSchema};
+use crate::image::derivative::{has_alpha, negotiate, render, DerivativeOptions, OutputFormat};
+use crate::image::process::{describe, is_variant_name, process_image, variant_name, Metadata};
+use crate::image::{
+    model::ImageModel,
+    schema::{ImageQuery, UpdateImageSchema},
+};
+use crate::image::files::{
+    acquire_blob, content_key, exists, existing_variants, lock_blob, move_files, rekey_blob, release_blob, remove_derivatives, remove_released,
+};
+use crate::image::process::parse_variant_name;
+use crate::storage::store::{validate_name, StorageError};
 use crate::AppState;
 
+fn configured_widths(data: &AppState) -> Vec<i32> {
+    data.config
+        .images
+        .variant_widths
+        .iter()
+        .map(|width| *width as i32)
+        .collect()
+}
+
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+        })
+}
+
+// Storage key of the image or variant served under `name`, variants as {stem}-{width}w.webp
+async fn resolve(data: &AppState, name: &str) -> Result<Option<String>, ApiError> {
+    let key = sqlx::query_scalar!("SELECT storage_key FROM images WHERE name = $1", name)
+        .fetch_optional(&data.db)
+        .await?;
+    if key.is_some() {
+        return Ok(key);
+    }
+
+    let Some((stem, width)) = parse_variant_name(name) else {
+        return Ok(None);
+    };
+    let key = sqlx::query_scalar!(
+        r"SELECT storage_key FROM images WHERE regexp_replace(name, '\.[^.]*$', '') = $1 AND $2 = ANY(variants) ORDER BY name LIMIT 1",
+        stem,
+        width
+    )
+    .fetch_optional(&data.db)
+    .await?;
+
+    Ok(key.map(|key| variant_name(&key, width)))
+}
+
+// Serves the image as stored, or resized and converted with ?w=&h=&fit=&q= and the Accept header
 pub async fn show_image_handler(
     Path(path): Path<String>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let file_path = format!("images/{}", path);
+    Query(query): Query<ImageQuery>,
+    headers: HeaderMap,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let not_found = || ApiError::NotFound("Image not found".to_string());
 
-    let file = match tokio::fs::File::open(&file_path).await {
-        Ok(file) => file,
-        Err(_) => return Err((StatusCode::NOT_FOUND, "Image not found".to_string())),
+    if validate_name(&path).is_err() {
+        return Err(not_found());
+    }
+    let key = resolve(&data, &path).await?.ok_or_else(not_found)?;
+
+    let source = match data.storage.stat(&key).await {
+        Ok(source) => source,
+        Err(StorageError::NotFound) => return Err(not_found()),
+        Err(e) => return Err(e.into()),
     };
-    // read file body stream
-    let stream = FramedRead::new(file, BytesCodec::new());
-    let file_body = reqwest::Body::wrap_stream(stream);
 
-    Ok(Response::new(file_body))
+    let options = DerivativeOptions::from_query(&query, &data.config.images)?;
+    let accept = headers.get(ACCEPT).and_then(|value| value.to_str().ok());
+    let source_format = OutputFormat::from_name(&key);
+    // Fetched at most once, and only when the response needs it
+    let source_bytes = OnceCell::new();
+    let load_source = || data.storage.get(&key);
+
+    let (format, cache_name) = match source_format {
+        // Files that aren't images are only ever served as they are
+        None if !options.resizes() => (None, None),
+        None => return Err(ApiError::BadRequest("Only images can be resized".to_string())),
+        _ => {
+            // Past AVIF and WebP the choice depends on transparency, which takes the source to know
+            let format = match negotiate(accept, || true) {
+                format @ (OutputFormat::Avif | OutputFormat::WebP) => format,
+                _ => {
+                    let bytes = source_bytes.get_or_try_init(load_source).await?;
+                    negotiate(accept, || has_alpha(bytes).unwrap_or(true))
+                }
+            };
+
+            if !options.resizes() && source_format == Some(format) {
+                (Some(format), None)
+            } else {
+                (Some(format), Some(options.cache_name(format)))
+            }
+        }
+    };
+
+    // Changes whenever the source is replaced, without reading either file
+    let modified_nanos = source.modified.timestamp_nanos_opt().unwrap_or_default();
+    let digest = Sha256::digest(format!(
+        "{}:{}:{}",
+        modified_nanos,
+        source.size,
+        cache_name.as_deref().unwrap_or_default()
+    ));
+    let etag = format!("\"{}\"", &hex::encode(digest)[..32]);
+
+    let last_modified = source.modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    let content_type = format.map_or("application/octet-stream", |format| format.content_type());
+
+    let response_headers = [
+        (CONTENT_TYPE, content_type.to_string()),
+        (ETAG, etag.clone()),
+        (LAST_MODIFIED, last_modified),
+        (
+            CACHE_CONTROL,
+            format!("public, max-age={}", data.config.images.cache_max_age_seconds),
+        ),
+        (VARY, "Accept".to_string()),
+    ];
+
+    if etag_matches(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, response_headers).into_response());
+    }
+
+    let bytes = match (cache_name, format) {
+        (Some(cache_name), Some(format)) => {
+            // Kept per storage key, so renaming an image keeps its cache and replacing it doesn't
+            let derivative = format!("{}/{}", key, cache_name);
+            let cached = match data.derivatives.stat(&derivative).await {
+                Ok(cached) => cached.modified >= source.modified,
+                Err(_) => false,
+            };
+
+            if cached {
+                data.derivatives.get(&derivative).await?
+            } else {
+                let _permit = data.renders.acquire().await.map_err(ApiError::internal)?;
+
+                // Another request may have rendered it while this one waited
+                match data.derivatives.stat(&derivative).await {
+                    Ok(cached) if cached.modified >= source.modified => data.derivatives.get(&derivative).await?,
+                    _ => {
+                        let source = source_bytes.get_or_try_init(load_source).await?.clone();
+                        let bytes = tokio::task::spawn_blocking(move || render(&source, &options, format))
+                            .await
+                            .map_err(ApiError::internal)??;
+
+                        data.derivatives.put(&derivative, bytes.clone()).await?;
+                        bytes
+                    }
+                }
+            }
+        }
+        _ => match source_bytes.into_inner() {
+            Some(bytes) => bytes,
+            None => load_source().await?,
+        },
+    };
+
+    Ok((response_headers, Body::from(bytes)).into_response())
 }
 
+// Stores the upload under a key derived from its content and returns the id that registers it
 pub async fn upload_image_handler(
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
     mut multipart: Multipart,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    while let Some(mut field) = multipart.next_field().await.unwrap() {
-        // Get the name of the file
-        let name = field.name().unwrap().to_string();
-
-        let file_path = format!("images/{}.webp", name);
-        let mut file = File::create(&file_path).unwrap();
-
-        // Write the data from the field to the file
-        while let Some(chunk) = field.next().await {
-            let data = chunk.unwrap();
-            file.write_all(&data).unwrap();
+) -> Result<impl IntoResponse, ApiError> {
+    if let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ApiError::Validation(e.body_text()))?
+    {
+        // The field and file names are never used as a path
+        let bytes = field
+            .bytes()
+            .await
+            .map_err(|e| ApiError::Validation(e.body_text()))?;
+
+        let images_config = data.config.images.clone();
+        let processed = tokio::task::spawn_blocking(move || process_image(&bytes, &images_config))
+            .await
+            .map_err(ApiError::internal)??;
+
+        let metadata = processed.metadata;
+        let storage_key = content_key(metadata.sha256.as_deref().unwrap_or_default());
+        let variants: Vec<i32> = processed.variants.iter().map(|variant| variant.width).collect();
+
+        // Written before anything is counted, so no transaction waits on storage. Storing identical
+        // bytes again is harmless and brings back files that went missing. A failure leaves new files
+        // to the janitor
+        let mut files = vec![(storage_key.clone(), processed.bytes)];
+        files.extend(
+            processed
+                .variants
+                .into_iter()
+                .map(|variant| (variant_name(&storage_key, variant.width), variant.bytes)),
+        );
+        for (key, bytes) in &files {
+            data.storage.put(key, bytes.clone()).await?;
+        }
+
+        let mut tx = data.db.begin().await?;
+        // Releasing the last reference may have removed the files since they were written. No removal
+        // runs while this holds the blob's lock, so whatever is missing now is written once more
+        if acquire_blob(&mut tx, &storage_key, &variants).await? {
+            for (key, bytes) in files {
+                if !exists(&*data.storage, &key).await? {
+                    data.storage.put(&key, bytes).await?;
+                }
+            }
         }
+
+        let upload = sqlx::query!(
+            "INSERT INTO pending_uploads (storage_key,variants,width,height,mime_type,byte_size,sha256,placeholder,uploaded_by,expires_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW() + make_interval(secs => $10)) RETURNING id, expires_at",
+            storage_key,
+            &variants,
+            metadata.width,
+            metadata.height,
+            metadata.mime_type,
+            metadata.byte_size,
+            metadata.sha256,
+            metadata.placeholder,
+            principal.id,
+            data.config.images.pending_upload_ttl_seconds as f64
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let existing = sqlx::query_as!(
+            ImageModel,
+            "SELECT * FROM images WHERE storage_key = $1 ORDER BY created_at, id LIMIT 1",
+            storage_key
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        log::record(
+            &data.db,
+            Entry {
+                actor_id: Some(principal.id),
+                action: Action::Upload,
+                resource: "images",
+                resource_id: None,
+                changes: json!({"after": {"upload_id": upload.id, "storage_key": storage_key, "variants": variants}}),
+            },
+        )
+        .await;
+
         let item_response = json!({"status": "success","data": json!({
             "item": json!({
-                "name":name+".webp"
+                "upload_id": upload.id,
+                "width": metadata.width,
+                "height": metadata.height,
+                "mime_type": metadata.mime_type,
+                "byte_size": metadata.byte_size,
+                "sha256": metadata.sha256,
+                "placeholder": metadata.placeholder,
+                "variants": variants,
+                "expires_at": upload.expires_at,
+                "existing_image": existing
             })
         })});
 
-        return Ok((StatusCode::CREATED, Json(item_response)));
+        // Content already registered comes back with the image it belongs to
+        let status = if existing.is_some() { StatusCode::OK } else { StatusCode::CREATED };
+        return Ok((status, Json(item_response)));
     }
 
-    let json_response = serde_json::json!({
-        "status": "success",
-    });
-
-    Ok((StatusCode::CREATED, Json(json_response)))
+    Err(ApiError::Validation("Attach the image as a multipart file".to_string()))
 }
 
+// Registers a pending upload under `name`, or a file already stored as `name` when no upload_id is
+// given. Only rows change, so the image either exists afterwards or nothing happened
 pub async fn create_image_handler(
+    Extension(principal): Extension<Principal>,
     State(data): State<Arc<AppState>>,
     Json(body): Json<UpdateImageSchema>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    // Get the name of the file
-
-    let file_name = body.name.to_owned().unwrap();
-
-    let images_dir = PathBuf::from("images");
-
-    if std::path::Path::new("images/file.webp").exists() {
-        let old_file_path = images_dir.join("file.webp");
-
-        std::fs::rename(old_file_path, format!("images/{}", file_name)).unwrap();
-    } else {
-        let old_file_path = images_dir.join(file_name.to_owned());
-
-        std::fs::rename(old_file_path, format!("images/{}", file_name)).unwrap();
-    }
+) -> Result<impl IntoResponse, ApiError> {
+    let file_name = body
+        .name
+        .ok_or_else(|| ApiError::Validation("name is required".to_string()))?;
+    validate_name(&file_name)?;
+
+    let mut tx = data.db.begin().await?;
+
+    // A pending upload hands its reference to the image, a file stored as `name` gets its first one
+    let (storage_key, variants, metadata) = match body.upload_id {
+        Some(upload_id) => take_upload(&mut tx, upload_id).await?.into_parts(),
+        None => {
+            // Holds off a removal of the same file while it is checked and counted
+            lock_blob(&mut tx, &file_name).await?;
+            let storage = &*data.storage;
+            if !exists(storage, &file_name).await? {
+                return Err(ApiError::NotFound(format!("File {} not found", file_name)));
+            }
+            let variants = existing_variants(storage, &file_name, &configured_widths(&data)).await?;
+            let metadata = describe_stored(&data, &file_name).await?;
+            acquire_blob(&mut tx, &file_name, &variants).await?;
+            (file_name.clone(), variants, metadata)
+        }
+    };
 
-    let query_result = sqlx::query_as!(
+    let item = sqlx::query_as!(
         ImageModel,
-        "INSERT INTO images (name) VALUES ($1) RETURNING *",
+        "INSERT INTO images (name,storage_key,variants,width,height,mime_type,byte_size,sha256,placeholder,alt_text,caption) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) RETURNING *",
         file_name,
+        storage_key,
+        &variants,
+        metadata.width,
+        metadata.height,
+        metadata.mime_type,
+        metadata.byte_size,
+        metadata.sha256,
+        metadata.placeholder,
+        body.alt_text.unwrap_or_default(),
+        body.caption.unwrap_or_default()
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    log::record(
+        &data.db,
+        Entry {
+            actor_id: Some(principal.id),
+            action: Action::Create,
+            resource: "images",
+            resource_id: Some(item.id),
+            changes: log::diff(None, serde_json::to_value(&item).ok().as_ref(), &[]),
+        },
     )
-    .fetch_one(&data.db)
     .await;
 
-    match query_result {
-        Ok(item) => {
-            let item_response = json!({"status": "success","data": json!({
-                "item": item
-            })});
+    let item_response = json!({"status": "success","data": json!({
+        "item": item
+    })});
 
-            return Ok((StatusCode::CREATED, Json(item_response)));
-        }
-        Err(e) => {
-            if e.to_string()
-                .contains("duplicate key value violates unique constraint")
-            {
-                let error_response = serde_json::json!({
-                    "status": "fail",
-                    "message": "Note with that title already exists",
-                });
-                return Err((StatusCode::CONFLICT, Json(error_response)));
-            }
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"status": "error","message": format!("{:?}", e)})),
-            ));
-        }
+    Ok((StatusCode::CREATED, Json(item_response)))
+}
+
+struct PendingUpload {
+    storage_key: String,
+    variants: Vec<i32>,
+    width: Option<i32>,
+    height: Option<i32>,
+    mime_type: Option<String>,
+    byte_size: Option<i64>,
+    sha256: Option<String>,
+    placeholder: Option<String>,
+}
+
+impl PendingUpload {
+    fn into_parts(self) -> (String, Vec<i32>, Metadata) {
+        let metadata = Metadata {
+            width: self.width,
+            height: self.height,
+            mime_type: self.mime_type,
+            byte_size: self.byte_size,
+            sha256: self.sha256,
+            placeholder: self.placeholder,
+        };
+        (self.storage_key, self.variants, metadata)
     }
 }
 
+// Reads a file stored without going through the upload
+async fn describe_stored(data: &AppState, key: &str) -> Result<Metadata, ApiError> {
+    let bytes = data.storage.get(key).await?;
+    tokio::task::spawn_blocking(move || describe(&bytes))
+        .await
+        .map_err(ApiError::internal)
+}
+
+// Consumes the upload inside the caller's transaction, a rollback hands it back
+async fn take_upload(tx: &mut Transaction<'_, Postgres>, upload_id: Uuid) -> Result<PendingUpload, ApiError> {
+    sqlx::query_as!(
+        PendingUpload,
+        "DELETE FROM pending_uploads WHERE id = $1 AND expires_at > NOW() RETURNING storage_key, variants, width, height, mime_type, byte_size, sha256, placeholder",
+        upload_id
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("Upload {} not found or expired", upload_id)))
+}
+
+// Renames the image, edits its alt text and caption and/or replaces its file with a pending upload,
+// in one transaction
 pub async fn edit_image_handler(
-    Path(id): Path<uuid::Uuid>,
+    Path(id): Path<Uuid>,
+    Extension(principal): Extension<Principal>,
     State(data): State<Arc<AppState>>,
     Json(body): Json<UpdateImageSchema>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    let query_result = sqlx::query_as!(ImageModel, "SELECT * FROM images WHERE id = $1", id)
-        .fetch_one(&data.db)
-        .await;
+) -> Result<impl IntoResponse, ApiError> {
+    let mut tx = data.db.begin().await?;
 
-    if query_result.is_err() {
-        let error_response = serde_json::json!({
-            "status": "fail",
-            "message": format!("Item with ID: {} not found", id)
-        });
-        return Err((StatusCode::NOT_FOUND, Json(error_response)));
-    }
+    let item = sqlx::query_as!(ImageModel, "SELECT * FROM images WHERE id = $1 FOR UPDATE", id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| ApiError::not_found(id))?;
 
+    let before = serde_json::to_value(&item).ok();
     let now = chrono::Utc::now();
-    let item = query_result.unwrap();
-
-    let old_file_name = item.name;
 
-    let images_dir = PathBuf::from("images");
+    let new_file_name = body.name.unwrap_or(item.name.to_owned());
+    validate_name(&new_file_name)?;
 
-    if std::path::Path::new("images/file.webp").exists() {
-        let old_file_path = images_dir.join("file.webp");
-
-        std::fs::rename(
-            old_file_path,
-            format!("images/{}", body.name.to_owned().unwrap()),
-        )
-        .unwrap();
-    } else {
-        let old_file_path = images_dir.join(old_file_name.to_owned());
+    // Images registered from files already in storage are keyed by their name, the files follow a rename
+    let moves_file = body.upload_id.is_none() && item.storage_key == item.name && new_file_name != item.name;
+    if moves_file {
+        for key in std::iter::once(new_file_name.clone()).chain(item.variants.iter().map(|width| variant_name(&new_file_name, *width))) {
+            if exists(&*data.storage, &key).await? {
+                return Err(ApiError::Conflict(format!("A file named {} is already stored", key)));
+            }
+        }
+    }
 
-        std::fs::rename(
-            old_file_path,
-            format!("images/{}", body.name.to_owned().unwrap()),
-        )
-        .unwrap();
+    // Images registered from files already in storage are keyed by their name, the files follow a rename
+    let moves_file = body.upload_id.is_none() && item.storage_key == item.name && new_file_name != item.name;
+    if moves_file {
+        let targets = item.variants.iter().map(|width| variant_name(&new_file_name, *width));
+        for key in std::iter::once(new_file_name.clone()).chain(targets) {
+            if exists(&*data.storage, &key).await? {
+                return Err(ApiError::Conflict(format!("A file named {} is already stored", key)));
+            }
+        }
     }
 
-    let query_result = sqlx::query_as!(
+    let (storage_key, variants, metadata) = match body.upload_id {
+        Some(upload_id) => take_upload(&mut tx, upload_id).await?.into_parts(),
+        None => {
+            let metadata = Metadata {
+                width: item.width,
+                height: item.height,
+                mime_type: item.mime_type.clone(),
+                byte_size: item.byte_size,
+                sha256: item.sha256.clone(),
+                placeholder: item.placeholder.clone(),
+            };
+            let storage_key = if moves_file { new_file_name.clone() } else { item.storage_key.clone() };
+            (storage_key, item.variants.clone(), metadata)
+        }
+    };
+
+    let updated = sqlx::query_as!(
         ImageModel,
-        "UPDATE images SET name = $1, updated_at = $2 WHERE id = $3 RETURNING *",
-        body.name.to_owned().unwrap_or(old_file_name),
+        "UPDATE images SET name = $1, storage_key = $2, variants = $3, width = $4, height = $5, mime_type = $6, byte_size = $7, sha256 = $8, placeholder = $9, alt_text = $10, caption = $11, updated_at = $12 WHERE id = $13 RETURNING *",
+        new_file_name,
+        storage_key,
+        &variants,
+        metadata.width,
+        metadata.height,
+        metadata.mime_type,
+        metadata.byte_size,
+        metadata.sha256,
+        metadata.placeholder,
+        body.alt_text.unwrap_or(item.alt_text.to_owned()),
+        body.caption.unwrap_or(item.caption.to_owned()),
         now,
         id
     )
-    .fetch_one(&data.db)
-    .await;
+    .fetch_one(&mut *tx)
+    .await?;
 
-    match query_result {
-        Ok(item) => {
-            let item_response = serde_json::json!({"status": "success","data": serde_json::json!({
-                "item": item
-            })});
+    // The upload's reference replaces the image's old one, even when both point at the same file
+    let released = match body.upload_id {
+        Some(_) => release_blob(&mut tx, &item.storage_key).await?,
+        None => None,
+    };
 
-            return Ok(Json(item_response));
-        }
-        Err(err) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"status": "error","message": format!("{:?}", err)})),
-            ));
+    if moves_file {
+        rekey_blob(&mut tx, &item.storage_key, &storage_key).await?;
+        // The row is only committed once the files are in place, and they go back if it can't be
+        move_files(&data, &item.storage_key, &storage_key, &variants).await?;
+        if let Err(e) = tx.commit().await {
+            let _ = move_files(&data, &storage_key, &item.storage_key, &variants).await;
+            return Err(e.into());
         }
+        remove_derivatives(&data, &item.storage_key).await;
+    } else {
+        tx.commit().await?;
     }
-}
 
-pub async fn image_list_handler(
-    opts: Option<Query<FilterOptions>>,
-    State(data): State<Arc<AppState>>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    let Query(opts) = opts.unwrap_or_default();
+    // Files go only once no committed row points at them
+    if let Some(variants) = released {
+        remove_released(&data, &item.storage_key, &variants).await?;
+    }
 
-    let limit = opts.limit.unwrap_or(10);
-    let offset = (opts.page.unwrap_or(1) - 1) * limit;
+    log::record(
+        &data.db,
+        Entry {
+            actor_id: Some(principal.id),
+            action: Action::Update,
+            resource: "images",
+            resource_id: Some(id),
+            changes: log::diff(before.as_ref(), serde_json::to_value(&updated).ok().as_ref(), &[]),
+        },
+    )
+    .await;
 
-    let query_result = sqlx::query_as!(Table, "SELECT count(id) as count FROM images")
-        .fetch_one(&data.db)
-        .await;
+    let item_response = serde_json::json!({"status": "success","data": serde_json::json!({
+        "item": updated
+    })});
 
-    if query_result.is_err() {
-        let error_response = serde_json::json!({
-            "status": "fail",
-            "message": format!("Something went wrong")
-        });
-        return Err((StatusCode::NOT_FOUND, Json(error_response)));
-    }
+    Ok(Json(item_response))
+}
 
-    let item = query_result.unwrap();
+pub async fn image_list_handler(
+    Query(opts): Query<FilterOptions>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (limit, offset) = data.config.pagination.page(opts.page, opts.limit);
 
-    let count = item.count;
+    let count = sqlx::query_as!(Table, "SELECT count(id) as count FROM images")
+        .fetch_one(&data.db)
+        .await?
+        .count;
 
-    let query_result = sqlx::query_as!(
+    let items = sqlx::query_as!(
         ImageModel,
-        "SELECT * FROM images ORDER by created_at LIMIT $1 OFFSET $2",
-        limit as i32,
-        offset as i32
+        "SELECT * FROM images ORDER by created_at, id LIMIT $1 OFFSET $2",
+        limit,
+        offset
     )
     .fetch_all(&data.db)
-    .await;
-
-    if query_result.is_err() {
-        let error_response = serde_json::json!({
-            "status": "fail",
-            "message": "Something bad happened while fetching all items",
-        });
-        return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)));
-    }
-
-    let items = query_result.unwrap();
+    .await?;
 
     let json_response = serde_json::json!({
         "status": "success",
@@ -242,109 +572,146 @@ pub async fn image_list_handler(
 }
 
 pub async fn get_image_handler(
-    Path(id): Path<uuid::Uuid>,
+    Path(id): Path<Uuid>,
     State(data): State<Arc<AppState>>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    let query_result = sqlx::query_as!(ImageModel, "SELECT * FROM images WHERE id = $1", id)
-        .fetch_one(&data.db)
-        .await;
+) -> Result<impl IntoResponse, ApiError> {
+    let item = sqlx::query_as!(ImageModel, "SELECT * FROM images WHERE id = $1", id)
+        .fetch_optional(&data.db)
+        .await?
+        .ok_or_else(|| ApiError::not_found(id))?;
 
-    match query_result {
-        Ok(item) => {
-            let item_response = serde_json::json!({"status": "success","data": serde_json::json!({
-                "item": item
-            })});
+    let item_response = serde_json::json!({"status": "success","data": serde_json::json!({
+        "item": item
+    })});
 
-            return Ok(Json(item_response));
-        }
-        Err(_) => {
-            let error_response = serde_json::json!({
-                "status": "fail",
-                "message": format!("Item with ID: {} not found", id)
-            });
-            return Err((StatusCode::NOT_FOUND, Json(error_response)));
-        }
-    }
+    Ok(Json(item_response))
 }
 
 pub async fn delete_image_handler(
-    Path(id): Path<uuid::Uuid>,
+    Path(id): Path<Uuid>,
+    Extension(principal): Extension<Principal>,
     State(data): State<Arc<AppState>>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    let query_result = sqlx::query_as!(ImageModel, "SELECT * FROM images WHERE id = $1", id)
-        .fetch_one(&data.db)
-        .await;
-    match query_result {
-        Ok(item) => {
-            let file_name = item.name;
-            let images_dir = PathBuf::from("images");
-
-            match std::fs::remove_file(images_dir.join(file_name)) {
-                Ok(_) => {}
-                Err(_) => {}
-            }
-        }
-        Err(_) => {
-            let error_response = serde_json::json!({
-                "status": "fail",
-                "message": format!("Item with ID: {} not found", id)
-            });
-            return Err((StatusCode::NOT_FOUND, Json(error_response)));
-        }
-    }
+) -> Result<impl IntoResponse, ApiError> {
+    let mut tx = data.db.begin().await?;
 
-    let rows_affected = sqlx::query!("DELETE FROM images WHERE id = $1", id)
-        .execute(&data.db)
-        .await
-        .unwrap()
-        .rows_affected();
-
-    if rows_affected == 0 {
-        let error_response = serde_json::json!({
-            "status": "fail",
-            "message": format!("Item with ID: {} not found", id)
-        });
-        return Err((StatusCode::NOT_FOUND, Json(error_response)));
+    let item = sqlx::query_as!(ImageModel, "DELETE FROM images WHERE id = $1 RETURNING *", id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| ApiError::not_found(id))?;
+
+    let before = serde_json::to_value(&item).ok();
+
+    // The file goes with the last image or pending upload pointing at it, after the row
+    let released = release_blob(&mut tx, &item.storage_key).await?;
+
+    tx.commit().await?;
+
+    if let Some(variants) = released {
+        remove_released(&data, &item.storage_key, &variants).await?;
     }
 
+    log::record(
+        &data.db,
+        Entry {
+            actor_id: Some(principal.id),
+            action: Action::Delete,
+            resource: "images",
+            resource_id: Some(id),
+            changes: log::diff(before.as_ref(), None, &[]),
+        },
+    )
+    .await;
+
     Ok(StatusCode::NO_CONTENT)
 }
 
+// Registers files stored directly under their name that no image points at yet, and reads the
+// metadata of images registered before it was recorded
 pub async fn update_all_images_handler(
     State(data): State<Arc<AppState>>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    let images = std::path::Path::new("images");
-
-    let paths = std::fs::read_dir(images).unwrap();
-
-    for path in paths {
-        if let Ok(entry) = path {
-            if let Some(file_name) = entry.path().file_name() {
-                if let Some(file_name_str) = file_name.to_str() {
-                    let query_result = sqlx::query_as!(
-                        ImageModel,
-                        "SELECT * FROM images WHERE name = $1",
-                        file_name_str
-                    )
-                    .fetch_one(&data.db)
-                    .await;
-
-                    match query_result {
-                        Ok(_item) => {}
-                        Err(_) => {
-                            sqlx::query_as!(
-                                ImageModel,
-                                "INSERT INTO images (name) VALUES ($1) RETURNING *",
-                                file_name_str,
-                            )
-                            .fetch_one(&data.db)
-                            .await
-                            .unwrap();
-                        }
-                    }
-                }
-            }
+    Extension(principal): Extension<Principal>,
+) -> Result<impl IntoResponse, ApiError> {
+    let storage = &*data.storage;
+    let widths = configured_widths(&data);
+    let mut inserted_ids = Vec::new();
+    let mut updated_ids = Vec::new();
+
+    for name in storage.list("").await? {
+        // Variants belong to the image they were made from, uploads are registered through their id
+        if name.contains('/') || is_variant_name(&name) {
+            continue;
+        }
+
+        let registered = sqlx::query_scalar!("SELECT EXISTS (SELECT 1 FROM images WHERE storage_key = $1 OR name = $1)", name)
+            .fetch_one(&data.db)
+            .await?
+            .unwrap_or(true);
+        if registered {
+            continue;
+        }
+
+        let metadata = describe_stored(&data, &name).await?;
+        let variants = existing_variants(storage, &name, &widths).await?;
+
+        let mut tx = data.db.begin().await?;
+        let inserted = sqlx::query_scalar!(
+            "INSERT INTO images (name,storage_key,variants,width,height,mime_type,byte_size,sha256,placeholder) VALUES ($1::text, $1::text, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT (name) DO NOTHING RETURNING id",
+            name,
+            &variants,
+            metadata.width,
+            metadata.height,
+            metadata.mime_type,
+            metadata.byte_size,
+            metadata.sha256,
+            metadata.placeholder
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(id) = inserted {
+            acquire_blob(&mut tx, &name, &variants).await?;
+            inserted_ids.push(id);
         }
+        tx.commit().await?;
+    }
+
+    let missing = sqlx::query!("SELECT id, storage_key FROM images WHERE sha256 IS NULL")
+        .fetch_all(&data.db)
+        .await?;
+
+    for image in missing {
+        // Files that have gone missing are left for whoever deletes the image
+        let metadata = match describe_stored(&data, &image.storage_key).await {
+            Err(ApiError::NotFound(_)) => continue,
+            metadata => metadata?,
+        };
+
+        sqlx::query!(
+            "UPDATE images SET width = $1, height = $2, mime_type = $3, byte_size = $4, sha256 = $5, placeholder = $6 WHERE id = $7",
+            metadata.width,
+            metadata.height,
+            metadata.mime_type,
+            metadata.byte_size,
+            metadata.sha256,
+            metadata.placeholder,
+            image.id
+        )
+        .execute(&data.db)
+        .await?;
+        updated_ids.push(image.id);
     }
+
+    log::record(
+        &data.db,
+        Entry {
+            actor_id: Some(principal.id),
+            action: Action::Update,
+            resource: "images",
+            resource_id: None,
+            changes: json!({"after": {"inserted": inserted_ids, "updated": updated_ids}}),
+        },
+    )
+    .await;
+
     Ok(StatusCode::OK)
 }
@@ -0,0 +1,78 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::error::ApiError;
+use crate::image::files::{release_blob, remove_files, remove_released, UPLOAD_PREFIX};
+use crate::image::process::variant_name;
+use crate::AppState;
+
+const INTERVAL_SECONDS: u64 = 300;
+
+// Runs for the lifetime of the server, see sweep
+pub async fn run(data: Arc<AppState>) {
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(INTERVAL_SECONDS));
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = sweep(&data).await {
+            eprintln!("Image janitor failed: {}", e);
+        }
+    }
+}
+
+// Removes expired pending uploads, then upload files no blob row counts. Those are left behind when
+// storing an upload fails or the server stops before it is recorded, and are only touched once they are
+// older than an upload may wait, so one still being recorded is never taken
+async fn sweep(data: &AppState) -> Result<(), ApiError> {
+    let mut tx = data.db.begin().await?;
+    let expired = sqlx::query_scalar!("DELETE FROM pending_uploads WHERE expires_at < NOW() RETURNING storage_key")
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let mut released = Vec::new();
+    for storage_key in &expired {
+        if let Some(variants) = release_blob(&mut tx, storage_key).await? {
+            released.push((storage_key, variants));
+        }
+    }
+    tx.commit().await?;
+
+    for (storage_key, variants) in released {
+        remove_released(data, storage_key, &variants).await?;
+    }
+
+    let rows = sqlx::query!("SELECT storage_key, variants FROM image_blobs")
+        .fetch_all(&data.db)
+        .await?;
+
+    let mut referenced = HashSet::new();
+    for row in rows {
+        for width in &row.variants {
+            referenced.insert(variant_name(&row.storage_key, *width));
+        }
+        referenced.insert(row.storage_key);
+    }
+
+    let cutoff = chrono::Utc::now() - chrono::Duration::seconds(data.config.images.pending_upload_ttl_seconds);
+    let mut orphans = 0;
+
+    for key in data.storage.list(&format!("{}/", UPLOAD_PREFIX)).await? {
+        if referenced.contains(&key) {
+            continue;
+        }
+        if data.storage.stat(&key).await.is_ok_and(|info| info.modified < cutoff) {
+            remove_files(data, &key, &[]).await;
+            orphans += 1;
+        }
+    }
+
+    if !expired.is_empty() || orphans > 0 {
+        println!(
+            "Image janitor removed {} expired uploads and {} orphaned files",
+            expired.len(),
+            orphans
+        );
+    }
+
+    Ok(())
+}
@@ -1,4 +1,8 @@
+pub mod derivative;
+pub mod files;
 pub mod handler;
+pub mod janitor;
 pub mod model;
+pub mod process;
 pub mod schema;
 pub mod route;
\ No newline at end of file
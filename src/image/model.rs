@@ -7,6 +7,19 @@ use uuid::Uuid;
 pub struct ImageModel {
     pub id: Uuid,    
     pub name: String,
+    // Where the file is stored, the name is only what it is served as
+    pub storage_key: String,
+    // Widths of the stored variants, served as {stem}-{width}w.webp
+    pub variants: Vec<i32>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub mime_type: Option<String>,
+    pub byte_size: Option<i64>,
+    pub sha256: Option<String>,
+    // Blurhash to show while the image loads
+    pub placeholder: Option<String>,
+    pub alt_text: String,
+    pub caption: String,
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
     pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
 }
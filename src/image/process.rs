@@ -0,0 +1,186 @@
+use std::io::Cursor;
+
+use image::{
+    error::ImageError, imageops::FilterType, DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+use crate::config::ImagesConfig;
+use crate::error::ApiError;
+
+// Recognised by their magic bytes, whatever the upload claims to be
+const ACCEPTED_FORMATS: [ImageFormat; 4] = [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP, ImageFormat::Gif];
+// Checked against the header before any pixels are allocated, so small files can't expand into huge ones
+const MAX_SOURCE_DIMENSION: u32 = 16384;
+// Blurhash components across and down, more only adds detail a placeholder doesn't need
+const PLACEHOLDER_COMPONENTS: (u32, u32) = (4, 3);
+// The hash is computed from a thumbnail this size, the result barely differs from the full image
+const PLACEHOLDER_SOURCE_SIZE: u32 = 64;
+
+lazy_static! {
+    static ref VARIANT_NAME: Regex = Regex::new(r"^(.+)-(\d+)w\.webp$").unwrap();
+}
+
+pub struct Variant {
+    pub width: i32,
+    pub bytes: Vec<u8>,
+}
+
+// What is stored about a file next to its name, NULL in the database when it couldn't be read
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub mime_type: Option<String>,
+    pub byte_size: Option<i64>,
+    pub sha256: Option<String>,
+    pub placeholder: Option<String>,
+}
+
+pub struct ProcessedImage {
+    pub bytes: Vec<u8>,
+    pub metadata: Metadata,
+    pub variants: Vec<Variant>,
+}
+
+// Decodes an upload and re-encodes it as WebP, CPU bound so callers run it with spawn_blocking
+pub fn process_image(bytes: &[u8], config: &ImagesConfig) -> Result<ProcessedImage, ApiError> {
+    let format = image::guess_format(bytes)
+        .ok()
+        .filter(|format| ACCEPTED_FORMATS.contains(format))
+        .ok_or_else(|| ApiError::Validation("Upload a PNG, JPEG, WebP or GIF image".to_string()))?;
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits);
+    let mut decoder = reader.into_decoder().map_err(invalid_image)?;
+
+    // Re-encoding drops EXIF, so the rotation it asked for is applied to the pixels instead
+    let orientation = decoder.orientation().map_err(invalid_image)?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(invalid_image)?;
+    image.apply_orientation(orientation);
+
+    if image.width() > config.max_dimension || image.height() > config.max_dimension {
+        image = image.resize(config.max_dimension, config.max_dimension, FilterType::Lanczos3);
+    }
+
+    let mut widths: Vec<u32> = config
+        .variant_widths
+        .iter()
+        .copied()
+        .filter(|width| *width < image.width())
+        .collect();
+    widths.sort_unstable();
+    widths.dedup();
+
+    let variants = widths
+        .into_iter()
+        .map(|width| {
+            let resized = image.resize(width, u32::MAX, FilterType::Lanczos3);
+            Ok(Variant {
+                width: width as i32,
+                bytes: encode_webp(&resized, config.webp_quality)?,
+            })
+        })
+        .collect::<Result<Vec<_>, ApiError>>()?;
+
+    let bytes = encode_webp(&image, config.webp_quality)?;
+    let mut metadata = file_metadata(&bytes);
+    metadata.mime_type = Some("image/webp".to_string());
+    add_image_metadata(&mut metadata, &image);
+
+    Ok(ProcessedImage {
+        bytes,
+        metadata,
+        variants,
+    })
+}
+
+// Metadata of a file that was stored without going through process_image, CPU bound like it. Files
+// that aren't images only get their size and checksum
+pub fn describe(bytes: &[u8]) -> Metadata {
+    let mut metadata = file_metadata(bytes);
+
+    let Ok(format) = image::guess_format(bytes) else {
+        return metadata;
+    };
+    metadata.mime_type = Some(format.to_mime_type().to_string());
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits);
+    if let Ok(image) = reader.decode() {
+        add_image_metadata(&mut metadata, &image);
+    }
+
+    metadata
+}
+
+fn file_metadata(bytes: &[u8]) -> Metadata {
+    Metadata {
+        byte_size: Some(bytes.len() as i64),
+        sha256: Some(hex::encode(Sha256::digest(bytes))),
+        ..Metadata::default()
+    }
+}
+
+fn add_image_metadata(metadata: &mut Metadata, image: &DynamicImage) {
+    metadata.width = Some(image.width() as i32);
+    metadata.height = Some(image.height() as i32);
+
+    let thumbnail = image.thumbnail(PLACEHOLDER_SOURCE_SIZE, PLACEHOLDER_SOURCE_SIZE).to_rgba8();
+    let (x, y) = PLACEHOLDER_COMPONENTS;
+    metadata.placeholder = blurhash::encode(x, y, thumbnail.width(), thumbnail.height(), thumbnail.as_raw()).ok();
+}
+
+// Lossy through libwebp, quality from 1 to 100. Photos stored lossless come out several times the
+// size of the JPEG they were uploaded as
+pub fn encode_webp(image: &DynamicImage, quality: u8) -> Result<Vec<u8>, ApiError> {
+    // The encoder only takes 8-bit RGB and RGBA
+    let memory = if image.color().has_alpha() {
+        let pixels = image.to_rgba8();
+        webp::Encoder::from_rgba(pixels.as_raw(), pixels.width(), pixels.height()).encode_simple(false, quality as f32)
+    } else {
+        let pixels = image.to_rgb8();
+        webp::Encoder::from_rgb(pixels.as_raw(), pixels.width(), pixels.height()).encode_simple(false, quality as f32)
+    };
+
+    memory
+        .map(|memory| memory.to_vec())
+        .map_err(|e| ApiError::Internal(format!("WebP encoding failed: {:?}", e)))
+}
+
+fn invalid_image(e: ImageError) -> ApiError {
+    match e {
+        ImageError::Limits(_) => ApiError::Validation(format!(
+            "Image is too large, the limit is {0}x{0} pixels",
+            MAX_SOURCE_DIMENSION
+        )),
+        _ => ApiError::Validation(format!("Image could not be decoded: {}", e)),
+    }
+}
+
+// hero.webp -> hero-320w.webp
+pub fn variant_name(name: &str, width: i32) -> String {
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    format!("{}-{}w.webp", stem, width)
+}
+
+pub fn is_variant_name(name: &str) -> bool {
+    VARIANT_NAME.is_match(name)
+}
+
+// hero-320w.webp -> ("hero", 320)
+pub fn parse_variant_name(name: &str) -> Option<(&str, i32)> {
+    let captures = VARIANT_NAME.captures(name)?;
+    let width = captures.get(2)?.as_str().parse().ok()?;
+    Some((captures.get(1)?.as_str(), width))
+}
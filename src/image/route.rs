@@ -1,11 +1,13 @@
     use std::sync::Arc;
 
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
 
 use crate::{
+    auth::{role::Access, route::require_permission},
     image::handler::{
         create_image_handler, delete_image_handler, edit_image_handler, get_image_handler,
         image_list_handler, show_image_handler, update_all_images_handler, upload_image_handler,
@@ -24,12 +26,14 @@ pub fn image_router(app_state: Arc<AppState>) -> Router {
                 .patch(edit_image_handler)
                 .delete(delete_image_handler),
         )
+        .route_layer(middleware::from_fn_with_state(Access::CONTENT, require_permission))
         .with_state(app_state)
 }
 
 pub fn admin_image_router(app_state: Arc<AppState>) -> Router {
     Router::new()
         .route("/update/all_images", get(update_all_images_handler))
+        .route_layer(middleware::from_fn_with_state(Access::PUBLISH, require_permission))
         .with_state(app_state)
 }
 
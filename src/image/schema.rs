@@ -1,11 +1,20 @@
 use serde::{ Deserialize, Serialize };
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct CreateImageSchema {
-    pub name: String,
-}
+use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UpdateImageSchema {
-    pub name: Option<String>,    
+    pub name: Option<String>,
+    // From POST /api/admin/image, registers that upload as the image's file
+    pub upload_id: Option<Uuid>,
+    pub alt_text: Option<String>,
+    pub caption: Option<String>,
 }
+
+// Read as strings so an empty `?h=` counts as not set instead of failing to parse
+#[derive(Deserialize, Debug, Default)]
+pub struct ImageQuery {
+    pub w: Option<String>,
+    pub h: Option<String>,
+    pub fit: Option<String>,
+    pub q: Option<String>,
+}
\ No newline at end of file
@@ -0,0 +1,38 @@
+use crate::{
+    error::ApiError,
+    resource::schema::{Fields, Filter, Resource},
+    job::{
+        model::JobModel,
+        schema::{CreateJobSchema, UpdateJobSchema},
+    },
+    AppState,
+};
+
+pub struct JobResource;
+
+impl Resource for JobResource {
+    type Model = JobModel;
+    type Create = CreateJobSchema;
+    type Update = UpdateJobSchema;
+
+    const TABLE: &'static str = "jobs";
+    const PATH: &'static str = "/jobs";
+    const SORTABLE: &'static [&'static str] = &["created_at", "updated_at", "date", "company", "title"];
+    const FILTERABLE: &'static [(&'static str, Filter)] = &[("company", Filter::Contains), ("title", Filter::Contains)];
+
+    async fn create_fields(body: CreateJobSchema, _: &AppState) -> Result<Fields, ApiError> {
+        Ok(Fields::new()
+            .set("company", body.company)
+            .set("title", body.title)
+            .set("date", body.date)
+            .set("description", body.description))
+    }
+
+    async fn update_fields(body: UpdateJobSchema, _: &AppState) -> Result<Fields, ApiError> {
+        Ok(Fields::new()
+            .set_some("company", body.company)
+            .set_some("title", body.title)
+            .set_some("date", body.date)
+            .set_some("description", body.description))
+    }
+}
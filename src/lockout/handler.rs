@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+use crate::extract::{Json, Path, Query};
+use crate::error::ApiError;
+use crate::general::schema::{FilterOptions, Table};
+use crate::lockout::model::LockoutModel;
+use crate::AppState;
+
+pub async fn lockout_list_handler(
+    Query(opts): Query<FilterOptions>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (limit, offset) = data.config.pagination.page(opts.page, opts.limit);
+
+    let count = sqlx::query_as!(Table, "SELECT count(id) as count FROM login_throttles")
+        .fetch_one(&data.db)
+        .await?
+        .count;
+
+    // Active lockouts first, then the most recent failures
+    let items = sqlx::query_as!(
+        LockoutModel,
+        "SELECT id,scope,subject,failures,last_failure_at,locked_until FROM login_throttles
+        ORDER BY locked_until > NOW() DESC NULLS LAST, last_failure_at DESC LIMIT $1 OFFSET $2",
+        limit,
+        offset
+    )
+    .fetch_all(&data.db)
+    .await?;
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "count": count,
+        "items": items
+    });
+    Ok(Json(json_response))
+}
+
+pub async fn delete_lockout_handler(
+    Path(id): Path<uuid::Uuid>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let rows_affected = sqlx::query!("DELETE FROM login_throttles WHERE id = $1", id)
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+
+    if rows_affected == 0 {
+        return Err(ApiError::not_found(id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+pub struct LockoutModel {
+    pub id: Uuid,
+    // "ip" or "account"
+    pub scope: String,
+    pub subject: String,
+    pub failures: i32,
+    pub last_failure_at: chrono::DateTime<chrono::Utc>,
+    pub locked_until: Option<chrono::DateTime<chrono::Utc>>,
+}
@@ -0,0 +1,17 @@
+use std::sync::Arc;
+
+use axum::{middleware, routing::{delete, get}, Router};
+
+use crate::{
+    auth::{role::Access, route::require_permission},
+    lockout::handler::{delete_lockout_handler, lockout_list_handler},
+    AppState,
+};
+
+pub fn lockout_router(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/lockouts", get(lockout_list_handler))
+        .route("/lockouts/:id", delete(delete_lockout_handler))
+        .route_layer(middleware::from_fn_with_state(Access::USERS, require_permission))
+        .with_state(app_state)
+}
@@ -0,0 +1,134 @@
+use std::{path::PathBuf, sync::Arc};
+
+use axum::async_trait;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::config::{ConfigError, MailConfig};
+
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), String>;
+}
+
+// Builds the transport selected by mail.transport
+pub fn from_config(config: &MailConfig) -> Result<Arc<dyn Mailer>, ConfigError> {
+    let from = config
+        .from
+        .parse::<Mailbox>()
+        .map_err(|e| ConfigError::Invalid(format!("mail.from: {}", e)))?;
+
+    let mailer: Arc<dyn Mailer> = match config.transport.as_str() {
+        "file" => Arc::new(FileMailer {
+            from,
+            dir: PathBuf::from(&config.dir),
+        }),
+        "smtp" => Arc::new(SmtpMailer::new(config, from)?),
+        _ => Arc::new(StdoutMailer { from }),
+    };
+
+    Ok(mailer)
+}
+
+fn build_message(from: &Mailbox, email: Email) -> Result<Message, String> {
+    let to = email.to.parse::<Mailbox>().map_err(|e| e.to_string())?;
+
+    Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(email.subject)
+        .body(email.body)
+        .map_err(|e| e.to_string())
+}
+
+// Prints messages instead of sending them, the default for local development
+pub struct StdoutMailer {
+    from: Mailbox,
+}
+
+#[async_trait]
+impl Mailer for StdoutMailer {
+    async fn send(&self, email: Email) -> Result<(), String> {
+        let message = build_message(&self.from, email)?;
+        println!("{}", String::from_utf8_lossy(&message.formatted()));
+        Ok(())
+    }
+}
+
+// Writes every message to its own .eml file, handy for tests that need to read the mail
+pub struct FileMailer {
+    from: Mailbox,
+    dir: PathBuf,
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), String> {
+        let message = build_message(&self.from, email)?;
+
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let file_name = format!(
+            "{}-{}.eml",
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.f"),
+            uuid::Uuid::new_v4()
+        );
+        tokio::fs::write(self.dir.join(file_name), message.formatted())
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+pub struct SmtpMailer {
+    from: Mailbox,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    fn new(config: &MailConfig, from: Mailbox) -> Result<SmtpMailer, ConfigError> {
+        let invalid = |e: lettre::transport::smtp::Error| ConfigError::Invalid(format!("mail: {}", e));
+
+        let builder = match config.smtp_tls.as_str() {
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host).map_err(invalid)?,
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host),
+            _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)
+                .map_err(invalid)?,
+        };
+
+        let mut builder = builder.port(config.smtp_port);
+        if !config.smtp_username.is_empty() {
+            builder = builder.credentials(Credentials::new(
+                config.smtp_username.clone(),
+                config.smtp_password.clone(),
+            ));
+        }
+
+        Ok(SmtpMailer {
+            from,
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), String> {
+        let message = build_message(&self.from, email)?;
+
+        self.transport
+            .send(message)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
@@ -8,44 +8,107 @@ mod detail;
 mod user;
 mod image;
 mod auth;
-
+mod public;
+mod lockout;
+mod audit;
+mod token;
+mod resource;
+
+mod config;
+mod error;
+mod extract;
+mod mailer;
 mod route;
+mod storage;
 
+use std::net::SocketAddr;
 use std::sync::Arc;
+use tokio::sync::Semaphore;
 
 use std::fs;
 use std::path::Path;
 
 use axum::http::{
     header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
-    HeaderValue, Method,
+    Method,
 };
+use auth::csrf::CSRF_HEADER;
+use config::Config;
+use mailer::Mailer;
+use storage::{local::LocalStorage, store::Storage};
 use dotenv::dotenv;
 use route::create_router;
-use tower_http::cors::CorsLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 
-use sqlx::{Pool, Postgres};
+use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
 
 use time::Duration;
 
 use tower_sessions::{session_store::ExpiredDeletion, Expiry, SessionManagerLayer};
-use tower_sessions_sqlx_store::{sqlx::PgPool, PostgresStore};
+use tower_sessions_sqlx_store::PostgresStore;
 
 pub struct AppState {
     db: Pool<Postgres>,
+    config: Config,
+    mailer: Arc<dyn Mailer>,
+    storage: Arc<dyn Storage>,
+    // Resized copies are a cache, kept on local disk whichever backend holds the images
+    derivatives: Arc<dyn Storage>,
+    // Bounds how many resized copies are rendered at once
+    renders: Semaphore,
 }
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
 
-    let images = Path::new("images");
-    if !images.exists() {
-        fs::create_dir(images).ok();
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mailer = match mailer::from_config(&config.mail) {
+        Ok(mailer) => mailer,
+        Err(e) => {
+            eprintln!("Invalid configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let storage = match storage::store::from_config(&config) {
+        Ok(storage) => storage,
+        Err(e) => {
+            eprintln!("Invalid configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let derivatives: Arc<dyn Storage> = Arc::new(LocalStorage::new(&config.images.derivative_dir));
+
+    for dir in [&config.upload_dir, &config.images.derivative_dir] {
+        let dir = Path::new(dir);
+        if !dir.exists() {
+            fs::create_dir_all(dir).ok();
+        }
+    }
+
+    let pool = PgPoolOptions::new()
+        .max_connections(config.database.max_connections)
+        .connect(&config.database.url)
+        .await
+        .unwrap();
+
+    if let Err(e) = sqlx::migrate!("./migrations").run(&pool).await {
+        eprintln!("Database migrations failed: {}", e);
+        std::process::exit(1);
     }
 
-    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let pool = PgPool::connect(&database_url).await.unwrap();
+    if std::env::args().any(|arg| arg == "--migrate-only") {
+        println!("Database migrations applied");
+        return;
+    }
  
     let session_store = PostgresStore::new(pool.clone());
     let session_store_result = session_store.migrate().await;
@@ -61,20 +124,43 @@ async fn main() {
     );     
  
     let session_layer = SessionManagerLayer::new(session_store)
-        .with_secure(true)
-        .with_expiry(Expiry::OnInactivity(Duration::seconds(1800)));
+        .with_secure(config.session.secure)
+        .with_http_only(config.session.http_only)
+        .with_same_site(config.session.same_site().unwrap())
+        .with_expiry(Expiry::OnInactivity(Duration::seconds(
+            config.session.expiry_seconds,
+        )));
 
     let cors = CorsLayer::new()
-        .allow_origin("http://localhost:5173".parse::<HeaderValue>().unwrap())
+        .allow_origin(AllowOrigin::list(config.origins()))
         .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
         .allow_credentials(true)
-        .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE]);
+        .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE, CSRF_HEADER]);
  
-    let app = create_router(Arc::new(AppState { db: pool.clone() })).layer(session_layer).layer(cors);
-
-    println!("🚀 Server started successfully");
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:1998").await.unwrap();
-    axum::serve(listener, app.into_make_service()).await.unwrap();
+    let bind_address = config.bind_address.clone();
+
+    let renders = Semaphore::new(config.images.max_concurrent_renders);
+    let app_state = Arc::new(AppState {
+        db: pool.clone(),
+        config,
+        mailer,
+        storage,
+        derivatives,
+        renders,
+    });
+
+    tokio::task::spawn(image::janitor::run(app_state.clone()));
+
+    let app = create_router(app_state)
+    .layer(session_layer)
+    .layer(cors);
+
+    #[cfg(feature = "dev-impersonation")]
+    eprintln!("{}", auth::impersonate::BANNER);
+
+    println!("🚀 Server started successfully on {}", bind_address);
+    let listener = tokio::net::TcpListener::bind(&bind_address).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
 
     deletion_task.await.unwrap().unwrap();
       
@@ -0,0 +1,42 @@
+use crate::{
+    error::ApiError,
+    resource::schema::{Fields, Filter, Resource},
+    project::{
+        model::ProjectModel,
+        schema::{CreateProjectSchema, UpdateProjectSchema},
+    },
+    AppState,
+};
+
+pub struct ProjectResource;
+
+impl Resource for ProjectResource {
+    type Model = ProjectModel;
+    type Create = CreateProjectSchema;
+    type Update = UpdateProjectSchema;
+
+    const TABLE: &'static str = "projects";
+    const PATH: &'static str = "/projects";
+    const SORTABLE: &'static [&'static str] = &["created_at", "updated_at", "title"];
+    const FILTERABLE: &'static [(&'static str, Filter)] = &[("title", Filter::Contains), ("description", Filter::Contains)];
+
+    async fn create_fields(body: CreateProjectSchema, _: &AppState) -> Result<Fields, ApiError> {
+        Ok(Fields::new()
+            .set("title", body.title)
+            .set("description", body.description)
+            .set("imgs", body.imgs)
+            .set("demo", body.demo)
+            .set("git", body.git)
+            .set("stacks", body.stacks))
+    }
+
+    async fn update_fields(body: UpdateProjectSchema, _: &AppState) -> Result<Fields, ApiError> {
+        Ok(Fields::new()
+            .set_some("title", body.title)
+            .set_some("description", body.description)
+            .set_some("imgs", body.imgs)
+            .set_some("demo", body.demo)
+            .set_some("git", body.git)
+            .set_some("stacks", body.stacks))
+    }
+}
@@ -0,0 +1,222 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    response::IntoResponse,
+};
+
+use crate::extract::{Json, Path, Query};
+use crate::error::ApiError;
+use crate::general::schema::{FilterOptions, Table};
+use crate::public::model::{
+    PublicDetailModel, PublicImageModel, PublicJobModel, PublicProjectModel, PublicTestimonialModel,
+};
+use crate::AppState;
+
+pub async fn public_project_list_handler(
+    Query(opts): Query<FilterOptions>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (limit, offset) = data.config.pagination.public_page(opts.page, opts.limit);
+
+    let count = sqlx::query_as!(Table, "SELECT count(id) as count FROM projects")
+        .fetch_one(&data.db)
+        .await?
+        .count;
+
+    let items = sqlx::query_as!(
+        PublicProjectModel,
+        "SELECT id,title,description,imgs,demo,git,stacks FROM projects ORDER by created_at, id LIMIT $1 OFFSET $2",
+        limit,
+        offset
+    )
+    .fetch_all(&data.db)
+    .await?;
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "count": count,
+        "items": items
+    });
+    Ok(Json(json_response))
+}
+
+pub async fn public_get_project_handler(
+    Path(id): Path<uuid::Uuid>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let item = sqlx::query_as!(
+        PublicProjectModel,
+        "SELECT id,title,description,imgs,demo,git,stacks FROM projects WHERE id = $1",
+        id
+    )
+    .fetch_optional(&data.db)
+    .await?
+    .ok_or_else(|| ApiError::not_found(id))?;
+
+    let item_response = serde_json::json!({"status": "success","data": serde_json::json!({
+        "item": item
+    })});
+    Ok(Json(item_response))
+}
+
+pub async fn public_job_list_handler(
+    Query(opts): Query<FilterOptions>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (limit, offset) = data.config.pagination.public_page(opts.page, opts.limit);
+
+    let count = sqlx::query_as!(Table, "SELECT count(id) as count FROM jobs")
+        .fetch_one(&data.db)
+        .await?
+        .count;
+
+    let items = sqlx::query_as!(
+        PublicJobModel,
+        "SELECT id,company,title,date,description FROM jobs ORDER by created_at, id LIMIT $1 OFFSET $2",
+        limit,
+        offset
+    )
+    .fetch_all(&data.db)
+    .await?;
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "count": count,
+        "items": items
+    });
+    Ok(Json(json_response))
+}
+
+pub async fn public_get_job_handler(
+    Path(id): Path<uuid::Uuid>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let item = sqlx::query_as!(
+        PublicJobModel,
+        "SELECT id,company,title,date,description FROM jobs WHERE id = $1",
+        id
+    )
+    .fetch_optional(&data.db)
+    .await?
+    .ok_or_else(|| ApiError::not_found(id))?;
+
+    let item_response = serde_json::json!({"status": "success","data": serde_json::json!({
+        "item": item
+    })});
+    Ok(Json(item_response))
+}
+
+pub async fn public_testimonial_list_handler(
+    Query(opts): Query<FilterOptions>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (limit, offset) = data.config.pagination.public_page(opts.page, opts.limit);
+
+    let count = sqlx::query_as!(Table, "SELECT count(id) as count FROM testimonials")
+        .fetch_one(&data.db)
+        .await?
+        .count;
+
+    let items = sqlx::query_as!(
+        PublicTestimonialModel,
+        "SELECT id,name,comment,position,company,img FROM testimonials ORDER by created_at, id LIMIT $1 OFFSET $2",
+        limit,
+        offset
+    )
+    .fetch_all(&data.db)
+    .await?;
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "count": count,
+        "items": items
+    });
+    Ok(Json(json_response))
+}
+
+pub async fn public_get_testimonial_handler(
+    Path(id): Path<uuid::Uuid>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let item = sqlx::query_as!(
+        PublicTestimonialModel,
+        "SELECT id,name,comment,position,company,img FROM testimonials WHERE id = $1",
+        id
+    )
+    .fetch_optional(&data.db)
+    .await?
+    .ok_or_else(|| ApiError::not_found(id))?;
+
+    let item_response = serde_json::json!({"status": "success","data": serde_json::json!({
+        "item": item
+    })});
+    Ok(Json(item_response))
+}
+
+// The site detail is a single row, the first one created wins
+pub async fn public_detail_handler(
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let item = sqlx::query_as!(
+        PublicDetailModel,
+        "SELECT title,logo,keywords,site_description,description,about,position,company,img FROM details ORDER by created_at, id LIMIT 1"
+    )
+    .fetch_optional(&data.db)
+    .await?;
+
+    let item = item.ok_or_else(|| ApiError::NotFound("Site detail not found".to_string()))?;
+
+    let item_response = serde_json::json!({"status": "success","data": serde_json::json!({
+        "item": item
+    })});
+    Ok(Json(item_response))
+}
+
+pub async fn public_image_list_handler(
+    Query(opts): Query<FilterOptions>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (limit, offset) = data.config.pagination.public_page(opts.page, opts.limit);
+
+    let count = sqlx::query_as!(Table, "SELECT count(id) as count FROM images")
+        .fetch_one(&data.db)
+        .await?
+        .count;
+
+    let items = sqlx::query_as!(
+        PublicImageModel,
+        "SELECT name,variants,width,height,mime_type,placeholder,alt_text,caption FROM images ORDER by created_at, id LIMIT $1 OFFSET $2",
+        limit,
+        offset
+    )
+    .fetch_all(&data.db)
+    .await?;
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "count": count,
+        "items": items
+    });
+    Ok(Json(json_response))
+}
+
+// By name, the way projects and the detail page refer to images
+pub async fn public_get_image_handler(
+    Path(name): Path<String>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let item = sqlx::query_as!(
+        PublicImageModel,
+        "SELECT name,variants,width,height,mime_type,placeholder,alt_text,caption FROM images WHERE name = $1",
+        name
+    )
+    .fetch_optional(&data.db)
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("Image {} not found", name)))?;
+
+    let item_response = serde_json::json!({"status": "success","data": serde_json::json!({
+        "item": item
+    })});
+    Ok(Json(item_response))
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+pub struct PublicProjectModel {
+    pub id: Uuid,
+    pub title: String,
+    pub description: String,
+    pub imgs: Vec<String>,
+    pub demo: String,
+    pub git: String,
+    pub stacks: Vec<String>,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+pub struct PublicJobModel {
+    pub id: Uuid,
+    pub company: String,
+    pub title: String,
+    pub date: String,
+    pub description: String,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+pub struct PublicTestimonialModel {
+    pub id: Uuid,
+    pub name: String,
+    pub comment: String,
+    pub position: String,
+    pub company: String,
+    pub img: String,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+pub struct PublicDetailModel {
+    pub title: String,
+    pub logo: String,
+    pub keywords: String,
+    pub site_description: String,
+    pub description: String,
+    pub about: String,
+    pub position: String,
+    pub company: String,
+    pub img: String,
+}
+
+// What the site needs to render an image, the file itself comes from /images/:name
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+pub struct PublicImageModel {
+    pub name: String,
+    pub variants: Vec<i32>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub mime_type: Option<String>,
+    pub placeholder: Option<String>,
+    pub alt_text: String,
+    pub caption: String,
+}
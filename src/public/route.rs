@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use axum::{
+    http::{header::CACHE_CONTROL, HeaderValue},
+    middleware,
+    response::Response,
+    routing::get,
+    Router,
+};
+
+use crate::{
+    public::handler::{
+        public_detail_handler, public_get_image_handler, public_get_job_handler,
+        public_get_project_handler, public_get_testimonial_handler, public_image_list_handler,
+        public_job_list_handler, public_project_list_handler, public_testimonial_list_handler,
+    },
+    AppState,
+};
+
+pub const PUBLIC_CACHE_CONTROL: &str = "public, max-age=300, stale-while-revalidate=60";
+
+async fn cache_headers(mut response: Response) -> Response {
+    let value = if response.status().is_success() {
+        HeaderValue::from_static(PUBLIC_CACHE_CONTROL)
+    } else {
+        HeaderValue::from_static("no-store")
+    };
+    response.headers_mut().insert(CACHE_CONTROL, value);
+    response
+}
+
+pub fn public_router(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/projects", get(public_project_list_handler))
+        .route("/projects/:id", get(public_get_project_handler))
+        .route("/jobs", get(public_job_list_handler))
+        .route("/jobs/:id", get(public_get_job_handler))
+        .route("/testimonials", get(public_testimonial_list_handler))
+        .route("/testimonials/:id", get(public_get_testimonial_handler))
+        .route("/detail", get(public_detail_handler))
+        .route("/images", get(public_image_list_handler))
+        .route("/images/:name", get(public_get_image_handler))
+        .with_state(app_state)
+        .layer(middleware::map_response(cache_headers))
+}
@@ -0,0 +1,218 @@
+use serde_json::json;
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+    Extension,
+};
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::extract::{Json, Path, Query};
+use crate::audit::log::{self, Action, Entry};
+use crate::auth::role::Principal;
+use crate::error::ApiError;
+use crate::resource::schema::{ListOptions, Resource};
+use crate::AppState;
+
+pub async fn list_handler<R: Resource>(
+    Query(params): Query<HashMap<String, String>>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let opts = ListOptions::from_query::<R>(params)?;
+
+    let (limit, offset) = data.config.pagination.page(opts.page, opts.limit);
+
+    let mut builder = QueryBuilder::<Postgres>::new(format!("SELECT count(id) as count FROM {}", R::TABLE));
+    opts.push_where(&mut builder);
+
+    let count = builder
+        .build_query_scalar::<i64>()
+        .fetch_one(&data.db)
+        .await?;
+
+    let mut builder = QueryBuilder::<Postgres>::new(format!("SELECT * FROM {}", R::TABLE));
+    opts.push_where(&mut builder);
+    builder.push(format!(
+        " ORDER BY {} {}, id LIMIT ",
+        opts.sort,
+        if opts.descending { "DESC" } else { "ASC" }
+    ));
+    builder.push_bind(limit);
+    builder.push(" OFFSET ");
+    builder.push_bind(offset);
+
+    let items = builder
+        .build_query_as::<R::Model>()
+        .fetch_all(&data.db)
+        .await?;
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "count": count,
+        "items": items
+    });
+    Ok(Json(json_response))
+}
+
+pub async fn create_handler<R: Resource>(
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<R::Create>,
+) -> Result<impl IntoResponse, ApiError> {
+    let fields = R::create_fields(body, &data).await?.0;
+    let columns: Vec<&str> = fields.iter().map(|(column, _)| *column).collect();
+
+    let mut builder = QueryBuilder::<Postgres>::new(format!("INSERT INTO {} (", R::TABLE));
+    let mut separated = builder.separated(", ");
+    for column in &columns {
+        separated.push(*column);
+    }
+    builder.push(") VALUES (");
+    for (index, (_, value)) in fields.into_iter().enumerate() {
+        if index > 0 {
+            builder.push(", ");
+        }
+        value.push_bind(&mut builder);
+    }
+    builder.push(") RETURNING *");
+
+    let item = builder
+        .build_query_as::<R::Model>()
+        .fetch_one(&data.db)
+        .await?;
+
+    let after = serde_json::to_value(&item).ok();
+    log::record(
+        &data.db,
+        Entry {
+            actor_id: Some(principal.id),
+            action: Action::Create,
+            resource: R::TABLE,
+            resource_id: after.as_ref().and_then(log::id_of),
+            changes: log::diff(None, after.as_ref(), &columns),
+        },
+    )
+    .await;
+
+    let item_response = json!({"status": "success","data": json!({
+        "item": item
+    })});
+
+    Ok((StatusCode::CREATED, Json(item_response)))
+}
+
+pub async fn get_handler<R: Resource>(
+    Path(id): Path<uuid::Uuid>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let item = sqlx::query_as::<_, R::Model>(&format!("SELECT * FROM {} WHERE id = $1", R::TABLE))
+        .bind(id)
+        .fetch_optional(&data.db)
+        .await?
+        .ok_or_else(|| ApiError::not_found(id))?;
+
+    let item_response = serde_json::json!({"status": "success","data": serde_json::json!({
+        "item": item
+    })});
+
+    Ok(Json(item_response))
+}
+
+pub async fn edit_handler<R: Resource>(
+    Path(id): Path<uuid::Uuid>,
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<R::Update>,
+) -> Result<impl IntoResponse, ApiError> {
+    let fields = R::update_fields(body, &data).await?.0;
+    let columns: Vec<&str> = fields.iter().map(|(column, _)| *column).collect();
+
+    let before = fetch_value::<R>(id, &data).await?;
+
+    let now = chrono::Utc::now();
+
+    let mut builder = QueryBuilder::<Postgres>::new(format!("UPDATE {} SET ", R::TABLE));
+    for (column, value) in fields {
+        builder.push(format!("{} = ", column));
+        value.push_bind(&mut builder);
+        builder.push(", ");
+    }
+    builder.push("updated_at = ");
+    builder.push_bind(now);
+    builder.push(" WHERE id = ");
+    builder.push_bind(id);
+    builder.push(" RETURNING *");
+
+    let item = builder
+        .build_query_as::<R::Model>()
+        .fetch_optional(&data.db)
+        .await?
+        .ok_or_else(|| ApiError::not_found(id))?;
+
+    R::after_update(&item, &data).await?;
+
+    let after = serde_json::to_value(&item).ok();
+    log::record(
+        &data.db,
+        Entry {
+            actor_id: Some(principal.id),
+            action: Action::Update,
+            resource: R::TABLE,
+            resource_id: Some(id),
+            changes: log::diff(before.as_ref(), after.as_ref(), &columns),
+        },
+    )
+    .await;
+
+    let item_response = serde_json::json!({"status": "success","data": serde_json::json!({
+        "item": item
+    })});
+
+    Ok(Json(item_response))
+}
+
+pub async fn delete_handler<R: Resource>(
+    Path(id): Path<uuid::Uuid>,
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let before = fetch_value::<R>(id, &data).await?;
+
+    R::before_delete(id, &data).await?;
+
+    let rows_affected = sqlx::query(&format!("DELETE FROM {} WHERE id = $1", R::TABLE))
+        .bind(id)
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+
+    if rows_affected == 0 {
+        return Err(ApiError::not_found(id));
+    }
+
+    log::record(
+        &data.db,
+        Entry {
+            actor_id: Some(principal.id),
+            action: Action::Delete,
+            resource: R::TABLE,
+            resource_id: Some(id),
+            changes: log::diff(before.as_ref(), None, &[]),
+        },
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// The row as the API shows it, for the audit log
+async fn fetch_value<R: Resource>(id: uuid::Uuid, data: &AppState) -> Result<Option<serde_json::Value>, ApiError> {
+    let item = sqlx::query_as::<_, R::Model>(&format!("SELECT * FROM {} WHERE id = $1", R::TABLE))
+        .bind(id)
+        .fetch_optional(&data.db)
+        .await?;
+
+    Ok(item.and_then(|item| serde_json::to_value(&item).ok()))
+}
@@ -0,0 +1,25 @@
+use std::sync::Arc;
+
+use axum::{middleware, routing::get, Router};
+
+use crate::{
+    auth::route::require_permission,
+    resource::{
+        handler::{create_handler, delete_handler, edit_handler, get_handler, list_handler},
+        schema::Resource,
+    },
+    AppState,
+};
+
+pub fn resource_router<R: Resource>(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(R::PATH, get(list_handler::<R>).post(create_handler::<R>))
+        .route(
+            &format!("{}/:id", R::PATH),
+            get(get_handler::<R>)
+                .patch(edit_handler::<R>)
+                .delete(delete_handler::<R>),
+        )
+        .route_layer(middleware::from_fn_with_state(R::ACCESS, require_permission))
+        .with_state(app_state)
+}
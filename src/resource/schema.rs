@@ -0,0 +1,178 @@
+use std::{collections::HashMap, future::Future};
+
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{postgres::PgRow, FromRow, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{auth::role::Access, error::ApiError, AppState};
+
+// A content type served by the generic CRUD handlers in `resource::handler`
+pub trait Resource: Send + Sync + 'static {
+    type Model: for<'r> FromRow<'r, PgRow> + Serialize + Send + Sync + Unpin;
+    type Create: DeserializeOwned + Send + 'static;
+    type Update: DeserializeOwned + Send + 'static;
+
+    const TABLE: &'static str;
+    const PATH: &'static str;
+    // The first column is the default sort
+    const SORTABLE: &'static [&'static str] = &["created_at", "updated_at"];
+    const FILTERABLE: &'static [(&'static str, Filter)] = &[];
+    const ACCESS: Access = Access::CONTENT;
+
+    fn create_fields(body: Self::Create, state: &AppState) -> impl Future<Output = Result<Fields, ApiError>> + Send;
+    fn update_fields(body: Self::Update, state: &AppState) -> impl Future<Output = Result<Fields, ApiError>> + Send;
+
+    fn after_update(
+        _item: &Self::Model,
+        _state: &AppState,
+    ) -> impl Future<Output = Result<(), ApiError>> + Send {
+        async { Ok(()) }
+    }
+
+    fn before_delete(_id: Uuid, _state: &AppState) -> impl Future<Output = Result<(), ApiError>> + Send {
+        async { Ok(()) }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Filter {
+    // Case insensitive substring match
+    Contains,
+    Equals,
+}
+
+pub enum Field {
+    Text(String),
+    SmallInt(i16),
+    TextArray(Vec<String>),
+}
+
+impl From<String> for Field {
+    fn from(value: String) -> Self {
+        Field::Text(value)
+    }
+}
+
+impl From<i16> for Field {
+    fn from(value: i16) -> Self {
+        Field::SmallInt(value)
+    }
+}
+
+impl From<Vec<String>> for Field {
+    fn from(value: Vec<String>) -> Self {
+        Field::TextArray(value)
+    }
+}
+
+impl Field {
+    pub fn push_bind(self, builder: &mut QueryBuilder<'_, Postgres>) {
+        match self {
+            Field::Text(value) => builder.push_bind(value),
+            Field::SmallInt(value) => builder.push_bind(value),
+            Field::TextArray(value) => builder.push_bind(value),
+        };
+    }
+}
+
+#[derive(Default)]
+pub struct Fields(pub Vec<(&'static str, Field)>);
+
+impl Fields {
+    pub fn new() -> Self {
+        Fields::default()
+    }
+
+    pub fn set(mut self, column: &'static str, value: impl Into<Field>) -> Self {
+        self.0.push((column, value.into()));
+        self
+    }
+
+    pub fn set_some<T: Into<Field>>(self, column: &'static str, value: Option<T>) -> Self {
+        match value {
+            Some(value) => self.set(column, value),
+            None => self,
+        }
+    }
+}
+
+pub struct ListOptions {
+    pub page: Option<usize>,
+    pub limit: Option<usize>,
+    pub sort: &'static str,
+    pub descending: bool,
+    pub filters: Vec<(&'static str, Filter, String)>,
+}
+
+impl ListOptions {
+    // page, limit, sort (prefix with - for descending) and any filterable column
+    pub fn from_query<R: Resource>(params: HashMap<String, String>) -> Result<Self, ApiError> {
+        let mut options = ListOptions {
+            page: parse_number(&params, "page")?,
+            limit: parse_number(&params, "limit")?,
+            sort: R::SORTABLE[0],
+            descending: false,
+            filters: Vec::new(),
+        };
+
+        if let Some(sort) = params.get("sort") {
+            let (column, descending) = match sort.strip_prefix('-') {
+                Some(column) => (column, true),
+                None => (sort.as_str(), false),
+            };
+            options.sort = R::SORTABLE
+                .iter()
+                .find(|sortable| **sortable == column)
+                .ok_or_else(|| {
+                    ApiError::Validation(format!(
+                        "Cannot sort by {}, expected one of {}",
+                        column,
+                        R::SORTABLE.join(", ")
+                    ))
+                })?;
+            options.descending = descending;
+        }
+
+        for (column, filter) in R::FILTERABLE {
+            if let Some(value) = params.get(*column) {
+                options.filters.push((column, *filter, value.to_owned()));
+            }
+        }
+
+        Ok(options)
+    }
+
+    pub fn push_where(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        for (index, (column, filter, value)) in self.filters.iter().enumerate() {
+            builder.push(if index == 0 { " WHERE " } else { " AND " });
+            match filter {
+                Filter::Contains => {
+                    builder.push(format!("{} ILIKE ", column));
+                    builder.push_bind(format!("%{}%", escape_like(value)));
+                }
+                Filter::Equals => {
+                    builder.push(format!("{}::text = ", column));
+                    builder.push_bind(value.to_owned());
+                }
+            }
+        }
+    }
+}
+
+fn parse_number(params: &HashMap<String, String>, key: &str) -> Result<Option<usize>, ApiError> {
+    params
+        .get(key)
+        .map(|value| {
+            value
+                .parse::<usize>()
+                .map_err(|_| ApiError::Validation(format!("{} must be a positive number", key)))
+        })
+        .transpose()
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
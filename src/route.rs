@@ -3,9 +3,11 @@ use std::sync::Arc;
 use axum::{middleware, Router};
 
 use crate::{
-    auth::route::{auth_admin, auth_router}, detail::route::detail_router, general::route::general_router,
-    image::route::{admin_image_router, image_router, visitor_image_router}, job::route::job_router, project::route::project_router,
-    testimonial::route::testimonial_router, user::route::user_router, AppState,
+    audit::route::audit_router, auth::route::{auth_admin, auth_router}, detail::route::detail_router, general::route::general_router,
+    image::route::{admin_image_router, image_router, visitor_image_router}, job::route::job_router, lockout::route::lockout_router, project::route::project_router,
+    public::route::public_router,
+    testimonial::route::testimonial_router, token::route::token_router, user::route::user_router,
+    AppState,
 };
 
 pub fn create_router(app_state: Arc<AppState>) -> Router {
@@ -18,6 +20,9 @@ pub fn create_router(app_state: Arc<AppState>) -> Router {
     let project_route = project_router(app_state.clone());
     let testimonial_route = testimonial_router(app_state.clone());
     let user_route = user_router(app_state.clone());
+    let lockout_route = lockout_router(app_state.clone());
+    let token_route = token_router(app_state.clone());
+    let audit_route = audit_router(app_state.clone());
 
     
     let admin_prefix = "/api/admin";
@@ -31,15 +36,24 @@ pub fn create_router(app_state: Arc<AppState>) -> Router {
     .nest(admin_prefix, project_route)
     .nest(admin_prefix, testimonial_route)
     .nest(admin_prefix, user_route)
-    .layer(middleware::from_fn(auth_admin));
+    .nest(admin_prefix, lockout_route)
+    .nest(admin_prefix, token_route)
+    .nest(admin_prefix, audit_route)
+    .layer(middleware::from_fn_with_state(app_state.clone(), auth_admin));
 
     let visitor_prefix = "/";
     
     let visitor_route = Router::new()
-    .nest(visitor_prefix, visitor_image_router(app_state))
+    .nest(visitor_prefix, visitor_image_router(app_state.clone()))
     .nest(visitor_prefix, auth_route);
 
+    let public_prefix = "/api/public";
+
+    let public_route = Router::new()
+    .nest(public_prefix, public_router(app_state));
+
     Router::new()
         .merge(admin_route)
         .merge(visitor_route)
+        .merge(public_route)
 }
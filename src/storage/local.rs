@@ -0,0 +1,174 @@
+use std::path::{Path, PathBuf};
+
+use axum::async_trait;
+
+use crate::storage::store::{validate_key, ObjectInfo, Storage, StorageError};
+
+// Files under a directory on this machine
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        LocalStorage {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+
+    async fn create_parent(path: &Path) -> Result<(), StorageError> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        Ok(())
+    }
+
+    // Drops directories a delete or rename left empty, up to the root
+    async fn prune(&self, path: &Path) {
+        let mut dir = path.parent();
+        while let Some(current) = dir {
+            if current == self.root || tokio::fs::remove_dir(current).await.is_err() {
+                break;
+            }
+            dir = current.parent();
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        Self::create_parent(&path).await?;
+
+        // Written aside and renamed, so a reader racing this never sees half a file. The dot keeps
+        // the temporary file out of listings
+        let temporary = path.with_file_name(format!(".{}.tmp", uuid::Uuid::new_v4()));
+        tokio::fs::write(&temporary, bytes).await?;
+        if let Err(e) = tokio::fs::rename(&temporary, &path).await {
+            let _ = tokio::fs::remove_file(&temporary).await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        Ok(tokio::fs::read(self.path(key)?).await?)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {
+                self.prune(&path).await;
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        let from = self.path(from)?;
+        let to = self.path(to)?;
+        Self::create_parent(&to).await?;
+        tokio::fs::rename(&from, &to).await?;
+        self.prune(&from).await;
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        // Only the directory the prefix points into needs walking
+        let start = prefix.rsplit_once('/').map_or("", |(dir, _)| dir);
+        let mut pending = vec![start.to_string()];
+        let mut keys = Vec::new();
+
+        while let Some(dir) = pending.pop() {
+            let mut entries = match tokio::fs::read_dir(self.root.join(&dir)).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            while let Some(entry) = entries.next_entry().await? {
+                let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                    continue;
+                };
+                let key = if dir.is_empty() { name } else { format!("{}/{}", dir, name) };
+                // Temporary and other hidden files aren't objects
+                if validate_key(&key).is_err() {
+                    continue;
+                }
+
+                let file_type = entry.file_type().await?;
+                if file_type.is_dir() {
+                    pending.push(key);
+                } else if file_type.is_file() && key.starts_with(prefix) {
+                    keys.push(key);
+                }
+            }
+        }
+
+        keys.sort();
+        Ok(keys)
+    }
+
+    async fn stat(&self, key: &str) -> Result<ObjectInfo, StorageError> {
+        let metadata = tokio::fs::metadata(self.path(key)?).await?;
+        if !metadata.is_file() {
+            return Err(StorageError::NotFound);
+        }
+
+        Ok(ObjectInfo {
+            size: metadata.len(),
+            modified: metadata.modified()?.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn storage() -> (LocalStorage, PathBuf) {
+        let root = std::env::temp_dir().join(format!("local-storage-{}", uuid::Uuid::new_v4()));
+        (LocalStorage::new(&root), root)
+    }
+
+    #[tokio::test]
+    async fn rename_moves_the_file() {
+        let (storage, root) = storage();
+        storage.put("a/b/one.webp", b"one".to_vec()).await.unwrap();
+        storage.put("two.webp", b"two".to_vec()).await.unwrap();
+
+        storage.rename("a/b/one.webp", "c/one.webp").await.unwrap();
+        assert_eq!(storage.get("c/one.webp").await.unwrap(), b"one");
+        assert!(matches!(storage.get("a/b/one.webp").await, Err(StorageError::NotFound)));
+        // The directories the file left are pruned
+        assert!(!root.join("a").exists());
+
+        storage.rename("c/one.webp", "two.webp").await.unwrap();
+        assert_eq!(storage.get("two.webp").await.unwrap(), b"one");
+        assert_eq!(storage.list("").await.unwrap(), ["two.webp"]);
+
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rename_refuses_missing_files_and_bad_keys() {
+        let (storage, root) = storage();
+        storage.put("one.webp", b"one".to_vec()).await.unwrap();
+
+        assert!(matches!(storage.rename("missing.webp", "two.webp").await, Err(StorageError::NotFound)));
+        assert!(matches!(storage.rename("one.webp", "../two.webp").await, Err(StorageError::InvalidKey(_))));
+        assert!(matches!(storage.rename("../one.webp", "two.webp").await, Err(StorageError::InvalidKey(_))));
+        assert_eq!(storage.get("one.webp").await.unwrap(), b"one");
+
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+}
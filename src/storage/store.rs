@@ -0,0 +1,144 @@
+use std::{fmt, sync::Arc};
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::config::{Config, ConfigError};
+use crate::storage::{local::LocalStorage, s3::S3Storage};
+
+// Longest key S3 accepts
+const MAX_KEY_LENGTH: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct ObjectInfo {
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    NotFound,
+    InvalidKey(String),
+    Backend(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::NotFound => write!(f, "object not found"),
+            StorageError::InvalidKey(key) => write!(f, "invalid storage key {:?}", key),
+            StorageError::Backend(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<std::io::Error> for StorageError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => StorageError::NotFound,
+            _ => StorageError::Backend(e.to_string()),
+        }
+    }
+}
+
+// Keys are '/' separated paths relative to the storage root. Every method checks them with
+// validate_key, so a key can never reach outside the root
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), StorageError>;
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+    // Deleting a missing object is not an error
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+    // Replaces whatever is stored under the new key
+    async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError>;
+    // Every key under the prefix, at any depth
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError>;
+    async fn stat(&self, key: &str) -> Result<ObjectInfo, StorageError>;
+}
+
+// Builds the backend selected by storage.backend, rooted at upload_dir or the bucket
+pub fn from_config(config: &Config) -> Result<Arc<dyn Storage>, ConfigError> {
+    let storage: Arc<dyn Storage> = match config.storage.backend.as_str() {
+        "s3" => Arc::new(S3Storage::new(&config.storage)?),
+        _ => Arc::new(LocalStorage::new(&config.upload_dir)),
+    };
+
+    Ok(storage)
+}
+
+// Rejects anything that could climb out of the root or hide from listings: empty segments (and so
+// leading, trailing or doubled slashes), dot segments, backslashes and control characters
+pub fn validate_key(key: &str) -> Result<(), StorageError> {
+    let valid = !key.is_empty()
+        && key.len() <= MAX_KEY_LENGTH
+        && key.split('/').all(|segment| {
+            !segment.is_empty()
+                && !segment.starts_with('.')
+                && !segment.contains(|c: char| c == '\\' || c.is_control())
+        });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidKey(key.to_string()))
+    }
+}
+
+// A key without directories, what user supplied file names have to be
+pub fn validate_name(name: &str) -> Result<(), StorageError> {
+    if name.contains('/') {
+        return Err(StorageError::InvalidKey(name.to_string()));
+    }
+    validate_key(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_plain_keys() {
+        for key in ["hero.webp", "uploads/abc.webp", "uploads/abc.webp/320x0-contain-q80.avif", "a b.png", "x..y"] {
+            assert!(validate_key(key).is_ok(), "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn rejects_keys_leaving_the_root() {
+        let long = "a".repeat(MAX_KEY_LENGTH + 1);
+        for key in [
+            "",
+            "..",
+            "../etc/passwd",
+            "uploads/../../etc/passwd",
+            ".",
+            "./a",
+            ".x",
+            "uploads/.tmp",
+            "a//b",
+            "/a",
+            "a/",
+            "/",
+            "a\\b",
+            "..\\a",
+            "a\0b",
+            "a\nb",
+            "a\u{7f}b",
+            long.as_str(),
+        ] {
+            assert!(
+                matches!(validate_key(key), Err(StorageError::InvalidKey(_))),
+                "{:?}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn names_have_no_directories() {
+        assert!(validate_name("hero.webp").is_ok());
+        for name in ["uploads/a.webp", "a/b", "/a", "a/", "..", ".x", "a\\b", "a\tb"] {
+            assert!(validate_name(name).is_err(), "{:?}", name);
+        }
+    }
+}
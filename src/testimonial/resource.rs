@@ -0,0 +1,40 @@
+use crate::{
+    error::ApiError,
+    resource::schema::{Fields, Filter, Resource},
+    testimonial::{
+        model::TestimonialModel,
+        schema::{CreateTestimonialSchema, UpdateTestimonialSchema},
+    },
+    AppState,
+};
+
+pub struct TestimonialResource;
+
+impl Resource for TestimonialResource {
+    type Model = TestimonialModel;
+    type Create = CreateTestimonialSchema;
+    type Update = UpdateTestimonialSchema;
+
+    const TABLE: &'static str = "testimonials";
+    const PATH: &'static str = "/testimonials";
+    const SORTABLE: &'static [&'static str] = &["created_at", "updated_at", "name", "company"];
+    const FILTERABLE: &'static [(&'static str, Filter)] = &[("name", Filter::Contains), ("company", Filter::Contains)];
+
+    async fn create_fields(body: CreateTestimonialSchema, _: &AppState) -> Result<Fields, ApiError> {
+        Ok(Fields::new()
+            .set("name", body.name)
+            .set("comment", body.comment)
+            .set("position", body.position)
+            .set("company", body.company)
+            .set("img", body.img))
+    }
+
+    async fn update_fields(body: UpdateTestimonialSchema, _: &AppState) -> Result<Fields, ApiError> {
+        Ok(Fields::new()
+            .set_some("name", body.name)
+            .set_some("comment", body.comment)
+            .set_some("position", body.position)
+            .set_some("company", body.company)
+            .set_some("img", body.img))
+    }
+}
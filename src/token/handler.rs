@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+    Extension,
+};
+
+use crate::extract::{Json, Path, Query};
+use crate::auth::role::{Permission, Principal};
+use crate::auth::token::{generate_token, hash_token};
+use crate::error::ApiError;
+use crate::general::schema::{FilterOptions, Table};
+use crate::token::{
+    model::TokenModel,
+    schema::{CreateTokenSchema, DEFAULT_EXPIRY_DAYS, MAX_EXPIRY_DAYS},
+};
+use crate::AppState;
+
+// Users see their own tokens, users:manage sees everyone's
+pub async fn token_list_handler(
+    Query(opts): Query<FilterOptions>,
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (limit, offset) = data.config.pagination.page(opts.page, opts.limit);
+    let all = principal.can(Permission::UsersManage);
+
+    let count = sqlx::query_as!(
+        Table,
+        "SELECT count(id) as count FROM api_tokens WHERE $1 OR user_id = $2",
+        all,
+        principal.id
+    )
+    .fetch_one(&data.db)
+    .await?
+    .count;
+
+    let items = sqlx::query_as!(
+        TokenModel,
+        "SELECT id,user_id,name,prefix,scopes,expires_at,last_used_at,created_at FROM api_tokens
+        WHERE $1 OR user_id = $2 ORDER BY created_at DESC LIMIT $3 OFFSET $4",
+        all,
+        principal.id,
+        limit,
+        offset
+    )
+    .fetch_all(&data.db)
+    .await?;
+
+    let json_response = serde_json::json!({
+        "status": "success",
+        "count": count,
+        "items": items
+    });
+    Ok(Json(json_response))
+}
+
+pub async fn create_token_handler(
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<CreateTokenSchema>,
+) -> Result<impl IntoResponse, ApiError> {
+    // Otherwise a leaked token could mint longer lived ones
+    if principal.scopes.is_some() {
+        return Err(ApiError::Forbidden("Tokens cannot create tokens".to_string()));
+    }
+
+    let name = body.name.trim().to_string();
+    if name.is_empty() {
+        return Err(ApiError::Validation("name is required".to_string()));
+    }
+    if body.scopes.is_empty() {
+        return Err(ApiError::Validation("At least one scope is required".to_string()));
+    }
+
+    let mut scopes: Vec<String> = Vec::new();
+    for scope in &body.scopes {
+        let permission: Permission = scope.parse().map_err(ApiError::Validation)?;
+        if !principal.can(permission) {
+            return Err(ApiError::Forbidden(format!("Missing permission {}", permission)));
+        }
+        if !scopes.iter().any(|s| s == permission.as_str()) {
+            scopes.push(permission.as_str().to_string());
+        }
+    }
+
+    let days = body.expires_in_days.unwrap_or(DEFAULT_EXPIRY_DAYS);
+    if !(1..=MAX_EXPIRY_DAYS).contains(&days) {
+        return Err(ApiError::Validation(format!(
+            "expires_in_days must be between 1 and {}",
+            MAX_EXPIRY_DAYS
+        )));
+    }
+    let expires_at = chrono::Utc::now() + chrono::Duration::days(days);
+
+    let (token, prefix) = generate_token();
+
+    let item = sqlx::query_as!(
+        TokenModel,
+        "INSERT INTO api_tokens (user_id,name,token_hash,prefix,scopes,expires_at) VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id,user_id,name,prefix,scopes,expires_at,last_used_at,created_at",
+        principal.id,
+        name,
+        hash_token(&token),
+        prefix,
+        &scopes,
+        expires_at
+    )
+    .fetch_one(&data.db)
+    .await?;
+
+    // The plain token is only ever returned here
+    let item_response = serde_json::json!({"status": "success","data": serde_json::json!({
+        "item": item,
+        "token": token
+    })});
+
+    Ok((StatusCode::CREATED, Json(item_response)))
+}
+
+pub async fn delete_token_handler(
+    Path(id): Path<uuid::Uuid>,
+    Extension(principal): Extension<Principal>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let rows_affected = sqlx::query!(
+        "DELETE FROM api_tokens WHERE id = $1 AND ($2 OR user_id = $3)",
+        id,
+        principal.can(Permission::UsersManage),
+        principal.id
+    )
+    .execute(&data.db)
+    .await?
+    .rows_affected();
+
+    if rows_affected == 0 {
+        return Err(ApiError::not_found(id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,4 @@
+pub mod handler;
+pub mod model;
+pub mod schema;
+pub mod route;
\ No newline at end of file
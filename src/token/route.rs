@@ -0,0 +1,16 @@
+use std::sync::Arc;
+
+use axum::{routing::{delete, get}, Router};
+
+use crate::{
+    token::handler::{create_token_handler, delete_token_handler, token_list_handler},
+    AppState,
+};
+
+// Any authenticated user manages their own tokens, no extra permission needed
+pub fn token_router(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/tokens", get(token_list_handler).post(create_token_handler))
+        .route("/tokens/:id", delete(delete_token_handler))
+        .with_state(app_state)
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_EXPIRY_DAYS: i64 = 90;
+pub const MAX_EXPIRY_DAYS: i64 = 365;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateTokenSchema {
+    pub name: String,
+    // Permission names such as "content:publish"
+    pub scopes: Vec<String>,
+    pub expires_in_days: Option<i64>,
+}
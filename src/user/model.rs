@@ -7,7 +7,6 @@ use uuid::Uuid;
 pub struct UserModel {
     pub id: Uuid,
     pub username: String,
-    pub password: String,
     pub email: String,
     pub fullname: String,
     pub role: i16,
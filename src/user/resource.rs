@@ -0,0 +1,90 @@
+use uuid::Uuid;
+
+use crate::{
+    auth::{
+        password::{hash_password, validate_password},
+        role::{Access, Role},
+        session::revoke_user_sessions,
+    },
+    error::ApiError,
+    resource::schema::{Fields, Filter, Resource},
+    user::{
+        model::UserModel,
+        schema::{CreateUserSchema, UpdateUserSchema},
+    },
+    AppState,
+};
+
+pub struct UserResource;
+
+impl Resource for UserResource {
+    type Model = UserModel;
+    type Create = CreateUserSchema;
+    type Update = UpdateUserSchema;
+
+    const TABLE: &'static str = "users";
+    const PATH: &'static str = "/users";
+    const SORTABLE: &'static [&'static str] =
+        &["created_at", "updated_at", "username", "email", "fullname", "role"];
+    const FILTERABLE: &'static [(&'static str, Filter)] = &[
+        ("username", Filter::Contains),
+        ("email", Filter::Contains),
+        ("fullname", Filter::Contains),
+        ("role", Filter::Equals),
+        ("active", Filter::Equals),
+    ];
+    const ACCESS: Access = Access::USERS;
+
+    async fn create_fields(body: CreateUserSchema, state: &AppState) -> Result<Fields, ApiError> {
+        let password = hash_new_password(&body.password, state.config.password_cost).await?;
+        Role::try_from(body.role).map_err(ApiError::Validation)?;
+
+        Ok(Fields::new()
+            .set("username", body.username)
+            .set("password", password)
+            .set("email", body.email)
+            .set("fullname", body.fullname)
+            .set("role", body.role)
+            .set("avatar", body.avatar)
+            .set("notes", body.notes)
+            .set("active", body.active))
+    }
+
+    async fn update_fields(body: UpdateUserSchema, state: &AppState) -> Result<Fields, ApiError> {
+        let password = match body.password {
+            Some(password) => Some(hash_new_password(&password, state.config.password_cost).await?),
+            None => None,
+        };
+        if let Some(role) = body.role {
+            Role::try_from(role).map_err(ApiError::Validation)?;
+        }
+
+        Ok(Fields::new()
+            .set_some("username", body.username)
+            .set_some("password", password)
+            .set_some("email", body.email)
+            .set_some("fullname", body.fullname)
+            .set_some("role", body.role)
+            .set_some("avatar", body.avatar)
+            .set_some("notes", body.notes)
+            .set_some("active", body.active))
+    }
+
+    async fn after_update(item: &UserModel, state: &AppState) -> Result<(), ApiError> {
+        if item.active == 0 {
+            revoke_user_sessions(&state.db, item.id).await?;
+        }
+        Ok(())
+    }
+
+    async fn before_delete(id: Uuid, state: &AppState) -> Result<(), ApiError> {
+        revoke_user_sessions(&state.db, id).await?;
+        Ok(())
+    }
+}
+
+async fn hash_new_password(password: &str, cost: u32) -> Result<String, ApiError> {
+    validate_password(password).map_err(ApiError::Validation)?;
+
+    hash_password(password, cost).await.map_err(ApiError::internal)
+}
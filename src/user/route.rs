@@ -1,30 +1,19 @@
 use std::sync::Arc;
 
-use axum::{
-    middleware,
-    routing::{get, post},
-    Router,
-};
+use axum::{middleware, routing::delete, Router};
 
 use crate::{
-    auth::route::authenticate,
-    user::handler::{
-        create_user_handler, delete_user_handler, edit_user_handler, get_user_handler,
-        user_list_handler,
-    },
+    auth::{role::Access, route::require_permission},
+    resource::route::resource_router,
+    user::{handler::revoke_sessions_handler, resource::UserResource},
     AppState,
 };
 
 pub fn user_router(app_state: Arc<AppState>) -> Router {
-    Router::new()
-    .route("/users", get(user_list_handler))
-    .route("/users", post(create_user_handler))
-    .route(
-        "/users/:id",
-        get(get_user_handler)
-        .patch(edit_user_handler)
-        .delete(delete_user_handler),
-    )
-    .with_state(app_state)
-    .layer(middleware::from_fn(authenticate))
+    let sessions_router = Router::new()
+        .route("/users/:id/sessions", delete(revoke_sessions_handler))
+        .route_layer(middleware::from_fn_with_state(Access::USERS, require_permission))
+        .with_state(app_state.clone());
+
+    resource_router::<UserResource>(app_state).merge(sessions_router)
 }